@@ -0,0 +1,542 @@
+//! Leader-key sequences such as "Ctrl+Space, T". Every step is a global
+//! shortcut, so the OS hands it to GoBuddy instead of the focused app: the
+//! leader is registered all the time, the steps that may follow only while
+//! a sequence is under way. `hotkeys` does the registering; this module
+//! parses sequences and follows the steps pressed so far.
+//!
+//! Presses are followed through the input listener rather than the
+//! shortcuts, as it sees every key: one that isn't the next step breaks the
+//! sequence off, and a step pressed before its shortcut is registered still
+//! counts.
+
+use rdev::Key;
+use std::time::{Duration, Instant};
+
+/// How long the matcher waits for the next step of a sequence.
+pub(crate) const CHORD_TIMEOUT: Duration = Duration::from_millis(1_000);
+
+const FUNCTION_KEYS: [Key; 12] = [
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Modifiers {
+    pub(crate) ctrl: bool,
+    pub(crate) alt: bool,
+    pub(crate) shift: bool,
+    pub(crate) meta: bool,
+}
+
+impl Modifiers {
+    /// Records a modifier key going down or up. Returns false for other keys.
+    pub(crate) fn update(&mut self, key: Key, pressed: bool) -> bool {
+        let flag = match key {
+            Key::ControlLeft | Key::ControlRight => &mut self.ctrl,
+            Key::Alt | Key::AltGr => &mut self.alt,
+            Key::ShiftLeft | Key::ShiftRight => &mut self.shift,
+            Key::MetaLeft | Key::MetaRight => &mut self.meta,
+            _ => return false,
+        };
+        *flag = pressed;
+        true
+    }
+}
+
+/// One key press of a sequence, with the modifiers held at the time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ChordStep {
+    pub(crate) modifiers: Modifiers,
+    pub(crate) key: Key,
+}
+
+/// Parses a comma-separated sequence such as "Ctrl+Space, T". Keys follow
+/// the US layout; single-step sequences are left to global hotkeys.
+pub(crate) fn parse_chord(display: &str) -> Result<Vec<ChordStep>, String> {
+    let steps = display
+        .split(',')
+        .map(parse_step)
+        .collect::<Result<Vec<_>, _>>()?;
+    if steps.len() < 2 {
+        return Err(format!(
+            "\"{}\" has a single step; use a hotkey for that, or add a second step as in \"Ctrl+Space, T\"",
+            display.trim()
+        ));
+    }
+    Ok(steps)
+}
+
+/// Parses one step, which is also how a hotkey's accelerator reads.
+pub(crate) fn parse_step(step: &str) -> Result<ChordStep, String> {
+    let step = step.trim();
+    if step.is_empty() {
+        return Err("A chord has an empty step".into());
+    }
+
+    let mut modifiers = Modifiers::default();
+    let mut key = None;
+    for token in step.split('+').map(str::trim) {
+        match token.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => modifiers.ctrl = true,
+            "alt" | "option" => modifiers.alt = true,
+            "shift" => modifiers.shift = true,
+            "meta" | "super" | "cmd" | "command" | "win" | "windows" => modifiers.meta = true,
+            "commandorcontrol" | "cmdorctrl" if cfg!(target_os = "macos") => modifiers.meta = true,
+            "commandorcontrol" | "cmdorctrl" => modifiers.ctrl = true,
+            "" => return Err(format!("\"{}\" has an empty key", step)),
+            name => {
+                if key.is_some() {
+                    return Err(format!("\"{}\" presses more than one key", step));
+                }
+                let (parsed, shift) =
+                    parse_key(name).ok_or_else(|| format!("Unknown key \"{}\"", token))?;
+                modifiers.shift |= shift;
+                key = Some(parsed);
+            }
+        }
+    }
+
+    let key = key.ok_or_else(|| format!("\"{}\" has only modifiers", step))?;
+    Ok(ChordStep { modifiers, key })
+}
+
+/// `name` is lowercase. The flag is whether the key implies Shift, as for
+/// "?" or "Plus".
+fn parse_key(name: &str) -> Option<(Key, bool)> {
+    if let Some(number) = name.strip_prefix('f').and_then(|n| n.parse::<usize>().ok()) {
+        return FUNCTION_KEYS
+            .get(number.checked_sub(1)?)
+            .map(|key| (*key, false));
+    }
+
+    let key = match name {
+        "space" => Key::Space,
+        "enter" | "return" => Key::Return,
+        "tab" => Key::Tab,
+        "esc" | "escape" => Key::Escape,
+        "backspace" => Key::Backspace,
+        "delete" | "del" => Key::Delete,
+        "insert" => Key::Insert,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "up" | "arrowup" => Key::UpArrow,
+        "down" | "arrowdown" => Key::DownArrow,
+        "left" | "arrowleft" => Key::LeftArrow,
+        "right" | "arrowright" => Key::RightArrow,
+        // "," and "+" separate steps and keys, so they go by name.
+        "comma" => Key::Comma,
+        "plus" => return Some((Key::Equal, true)),
+        _ => {
+            let mut chars = name.chars();
+            let ch = chars.next()?;
+            return match chars.next() {
+                None => key_for_char(ch),
+                Some(_) => None,
+            };
+        }
+    };
+    Some((key, false))
+}
+
+/// The US-layout key that produces `ch`, and whether it needs Shift.
+fn key_for_char(ch: char) -> Option<(Key, bool)> {
+    const LETTERS: [Key; 26] = [
+        Key::KeyA,
+        Key::KeyB,
+        Key::KeyC,
+        Key::KeyD,
+        Key::KeyE,
+        Key::KeyF,
+        Key::KeyG,
+        Key::KeyH,
+        Key::KeyI,
+        Key::KeyJ,
+        Key::KeyK,
+        Key::KeyL,
+        Key::KeyM,
+        Key::KeyN,
+        Key::KeyO,
+        Key::KeyP,
+        Key::KeyQ,
+        Key::KeyR,
+        Key::KeyS,
+        Key::KeyT,
+        Key::KeyU,
+        Key::KeyV,
+        Key::KeyW,
+        Key::KeyX,
+        Key::KeyY,
+        Key::KeyZ,
+    ];
+    const DIGITS: [Key; 10] = [
+        Key::Num0,
+        Key::Num1,
+        Key::Num2,
+        Key::Num3,
+        Key::Num4,
+        Key::Num5,
+        Key::Num6,
+        Key::Num7,
+        Key::Num8,
+        Key::Num9,
+    ];
+
+    let mapped = match ch {
+        'a'..='z' => (LETTERS[(ch as u8 - b'a') as usize], false),
+        'A'..='Z' => (LETTERS[(ch as u8 - b'A') as usize], true),
+        '0'..='9' => (DIGITS[(ch as u8 - b'0') as usize], false),
+        ')' => (Key::Num0, true),
+        '!' => (Key::Num1, true),
+        '@' => (Key::Num2, true),
+        '#' => (Key::Num3, true),
+        '$' => (Key::Num4, true),
+        '%' => (Key::Num5, true),
+        '^' => (Key::Num6, true),
+        '&' => (Key::Num7, true),
+        '*' => (Key::Num8, true),
+        '(' => (Key::Num9, true),
+        ' ' => (Key::Space, false),
+        '\n' | '\r' => (Key::Return, false),
+        '\t' => (Key::Tab, false),
+        '-' => (Key::Minus, false),
+        '_' => (Key::Minus, true),
+        '=' => (Key::Equal, false),
+        '+' => (Key::Equal, true),
+        '[' => (Key::LeftBracket, false),
+        '{' => (Key::LeftBracket, true),
+        ']' => (Key::RightBracket, false),
+        '}' => (Key::RightBracket, true),
+        '\\' => (Key::BackSlash, false),
+        '|' => (Key::BackSlash, true),
+        ';' => (Key::SemiColon, false),
+        ':' => (Key::SemiColon, true),
+        '\'' => (Key::Quote, false),
+        '"' => (Key::Quote, true),
+        '`' => (Key::BackQuote, false),
+        '~' => (Key::BackQuote, true),
+        ',' => (Key::Comma, false),
+        '<' => (Key::Comma, true),
+        '.' => (Key::Dot, false),
+        '>' => (Key::Dot, true),
+        '/' => (Key::Slash, false),
+        '?' => (Key::Slash, true),
+        _ => return None,
+    };
+    Some(mapped)
+}
+
+/// What a pressed step did to the sequence under way.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ChordProgress<T> {
+    /// The step completed the sequence for this target.
+    Completed(T),
+    /// The step continues at least one sequence; these steps may come next.
+    Pending(Vec<ChordStep>),
+    /// The step fits no sequence.
+    Idle,
+}
+
+/// Follows pressed steps and reports when they complete one of the
+/// configured sequences. A step that arrives after the timeout, or that fits
+/// no sequence, drops what was pressed so far; it may still start a new one.
+///
+/// Sequences are expected not to be prefixes of each other, which
+/// `hotkeys::plan` checks.
+pub(crate) struct ChordMatcher<T> {
+    chords: Vec<(Vec<ChordStep>, T)>,
+    timeout: Duration,
+    progress: Vec<ChordStep>,
+    last_step_at: Option<Instant>,
+}
+
+impl<T: Clone> Default for ChordMatcher<T> {
+    fn default() -> Self {
+        Self::new(CHORD_TIMEOUT)
+    }
+}
+
+impl<T: Clone> ChordMatcher<T> {
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self {
+            chords: Vec::new(),
+            timeout,
+            progress: Vec::new(),
+            last_step_at: None,
+        }
+    }
+
+    /// Replaces the sequences and forgets any half-pressed one.
+    pub(crate) fn set_chords(&mut self, chords: Vec<(Vec<ChordStep>, T)>) {
+        self.chords = chords;
+        self.reset();
+    }
+
+    pub(crate) fn press(&mut self, step: ChordStep, now: Instant) -> ChordProgress<T> {
+        self.expire(now);
+
+        self.progress.push(step);
+        if !self.continues_a_chord() && self.progress.len() > 1 {
+            self.progress.clear();
+            self.progress.push(step);
+        }
+        if !self.continues_a_chord() {
+            self.reset();
+            return ChordProgress::Idle;
+        }
+
+        let completed = self
+            .chords
+            .iter()
+            .find(|(steps, _)| *steps == self.progress)
+            .map(|(_, target)| target.clone());
+        if let Some(target) = completed {
+            self.reset();
+            return ChordProgress::Completed(target);
+        }
+
+        self.last_step_at = Some(now);
+        ChordProgress::Pending(self.next_steps())
+    }
+
+    /// Whether a sequence is under way.
+    pub(crate) fn is_pending(&self) -> bool {
+        !self.progress.is_empty()
+    }
+
+    /// The distinct steps that continue the sequence under way.
+    pub(crate) fn next_steps(&self) -> Vec<ChordStep> {
+        let mut next: Vec<ChordStep> = Vec::new();
+        if !self.is_pending() {
+            return next;
+        }
+        for (steps, _) in &self.chords {
+            if let Some(step) = steps
+                .strip_prefix(self.progress.as_slice())
+                .and_then(<[ChordStep]>::first)
+            {
+                if !next.contains(step) {
+                    next.push(*step);
+                }
+            }
+        }
+        next
+    }
+
+    /// Drops the sequence under way once its next step is overdue. Returns
+    /// whether there was one to drop.
+    pub(crate) fn expire(&mut self, now: Instant) -> bool {
+        let overdue = self
+            .last_step_at
+            .is_some_and(|at| now.saturating_duration_since(at) >= self.timeout);
+        if overdue {
+            self.reset();
+        }
+        overdue
+    }
+
+    fn continues_a_chord(&self) -> bool {
+        self.chords
+            .iter()
+            .any(|(steps, _)| steps.starts_with(&self.progress))
+    }
+
+    fn reset(&mut self) {
+        self.progress.clear();
+        self.last_step_at = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(display: &str) -> ChordStep {
+        parse_step(display).unwrap()
+    }
+
+    fn matcher(chords: &[(&str, &'static str)]) -> ChordMatcher<&'static str> {
+        let mut matcher = ChordMatcher::new(CHORD_TIMEOUT);
+        matcher.set_chords(
+            chords
+                .iter()
+                .map(|(display, target)| (parse_chord(display).unwrap(), *target))
+                .collect(),
+        );
+        matcher
+    }
+
+    fn press(
+        matcher: &mut ChordMatcher<&'static str>,
+        display: &str,
+        at: Instant,
+    ) -> ChordProgress<&'static str> {
+        matcher.press(step(display), at)
+    }
+
+    #[test]
+    fn parses_steps_with_modifiers_and_named_keys() {
+        let steps = parse_chord(" Ctrl + Space ,t, Shift+F2 ").unwrap();
+        assert_eq!(
+            steps,
+            vec![
+                ChordStep {
+                    modifiers: Modifiers {
+                        ctrl: true,
+                        ..Default::default()
+                    },
+                    key: Key::Space,
+                },
+                ChordStep {
+                    modifiers: Modifiers::default(),
+                    key: Key::KeyT,
+                },
+                ChordStep {
+                    modifiers: Modifiers {
+                        shift: true,
+                        ..Default::default()
+                    },
+                    key: Key::F2,
+                },
+            ]
+        );
+        assert_eq!(step("?"), step("Shift+/"));
+    }
+
+    #[test]
+    fn rejects_malformed_sequences() {
+        assert!(parse_chord("Ctrl+Space").is_err());
+        assert!(parse_chord("Ctrl+Space, ").is_err());
+        assert!(parse_chord("Ctrl+Space, Ctrl").is_err());
+        assert!(parse_chord("Ctrl+Space, T+Y").is_err());
+        assert!(parse_chord("Ctrl+Space, Blorp").is_err());
+        assert!(parse_chord("Ctrl+Space, F13").is_err());
+    }
+
+    #[test]
+    fn completes_a_sequence_within_the_timeout() {
+        let mut matcher = matcher(&[
+            ("Ctrl+Space, T", "translate"),
+            ("Ctrl+Space, S", "summarize"),
+        ]);
+        let start = Instant::now();
+
+        assert_eq!(
+            press(&mut matcher, "Ctrl+Space", start),
+            ChordProgress::Pending(vec![step("T"), step("S")])
+        );
+        assert_eq!(
+            press(&mut matcher, "S", start + Duration::from_millis(300)),
+            ChordProgress::Completed("summarize")
+        );
+        // The sequence starts over once completed.
+        assert_eq!(
+            press(&mut matcher, "T", start + Duration::from_millis(400)),
+            ChordProgress::Idle
+        );
+    }
+
+    #[test]
+    fn pending_steps_follow_the_sequence_so_far() {
+        let mut matcher = matcher(&[
+            ("Ctrl+Space, T, Y", "tone"),
+            ("Ctrl+Space, T, N", "notes"),
+            ("Ctrl+Space, S", "summarize"),
+        ]);
+        let start = Instant::now();
+
+        press(&mut matcher, "Ctrl+Space", start);
+        assert_eq!(
+            press(&mut matcher, "T", start),
+            ChordProgress::Pending(vec![step("Y"), step("N")])
+        );
+        assert_eq!(
+            press(&mut matcher, "N", start),
+            ChordProgress::Completed("notes")
+        );
+    }
+
+    #[test]
+    fn a_step_pressed_straight_after_the_leader_completes() {
+        let mut matcher = matcher(&[("Ctrl+Space, T", "translate")]);
+        let start = Instant::now();
+
+        press(&mut matcher, "Ctrl+Space", start);
+        assert_eq!(
+            press(&mut matcher, "T", start),
+            ChordProgress::Completed("translate")
+        );
+    }
+
+    #[test]
+    fn an_unrelated_key_breaks_the_sequence_off() {
+        let mut matcher = matcher(&[("Ctrl+Space, T", "translate")]);
+        let start = Instant::now();
+
+        press(&mut matcher, "Ctrl+Space", start);
+        assert_eq!(press(&mut matcher, "A", start), ChordProgress::Idle);
+        assert!(!matcher.is_pending());
+        assert!(matcher.next_steps().is_empty());
+        assert_eq!(press(&mut matcher, "T", start), ChordProgress::Idle);
+    }
+
+    #[test]
+    fn a_late_step_starts_over() {
+        let mut matcher = matcher(&[("Ctrl+Space, T", "translate")]);
+        let start = Instant::now();
+
+        press(&mut matcher, "Ctrl+Space", start);
+        let late = start + CHORD_TIMEOUT + Duration::from_millis(1);
+        assert_eq!(press(&mut matcher, "T", late), ChordProgress::Idle);
+    }
+
+    #[test]
+    fn an_overdue_sequence_expires_once() {
+        let mut matcher = matcher(&[("Ctrl+Space, T", "translate")]);
+        let start = Instant::now();
+
+        assert!(!matcher.expire(start));
+        press(&mut matcher, "Ctrl+Space", start);
+        assert!(!matcher.expire(start + Duration::from_millis(300)));
+        assert!(matcher.expire(start + CHORD_TIMEOUT));
+        assert!(!matcher.expire(start + CHORD_TIMEOUT));
+    }
+
+    #[test]
+    fn modifiers_must_match_exactly() {
+        let mut matcher = matcher(&[("Ctrl+Space, T", "translate")]);
+        let start = Instant::now();
+
+        press(&mut matcher, "Ctrl+Space", start);
+        // Still holding Ctrl: Ctrl+T is not the T step.
+        assert_eq!(press(&mut matcher, "Ctrl+T", start), ChordProgress::Idle);
+        assert_eq!(press(&mut matcher, "T", start), ChordProgress::Idle);
+    }
+
+    #[test]
+    fn a_repeated_leader_starts_the_sequence_again() {
+        let mut matcher = matcher(&[("Ctrl+Space, T", "translate")]);
+        let start = Instant::now();
+
+        press(&mut matcher, "Ctrl+Space", start);
+        assert_eq!(
+            press(&mut matcher, "Ctrl+Space", start),
+            ChordProgress::Pending(vec![step("T")])
+        );
+        assert_eq!(
+            press(&mut matcher, "T", start),
+            ChordProgress::Completed("translate")
+        );
+    }
+}
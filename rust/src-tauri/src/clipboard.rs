@@ -0,0 +1,701 @@
+use arboard::{Clipboard, ImageData};
+use std::path::PathBuf;
+
+/// The user's clipboard, captured before a shortcut borrows it so their own
+/// copy can be put back afterwards. Every format the copying app offered is
+/// read through the platform's clipboard API and comes back together, so an
+/// image keeps its alt text and a file list its name, and formats GoBuddy
+/// knows nothing about survive too.
+pub(crate) enum ClipboardSnapshot {
+    Formats(platform::Formats),
+    /// Used when the platform clipboard can't be read, e.g. on a Wayland
+    /// session without XWayland. Only one of these comes back: see
+    /// `restore`.
+    Basic {
+        text: Option<String>,
+        html: Option<String>,
+        image: Option<ImageData<'static>>,
+        files: Option<Vec<PathBuf>>,
+    },
+}
+
+impl ClipboardSnapshot {
+    pub(crate) fn capture(clipboard: &mut Clipboard) -> Self {
+        match platform::capture() {
+            Ok(formats) => return ClipboardSnapshot::Formats(formats),
+            Err(error) => eprintln!("Failed to read every clipboard format: {}", error),
+        }
+        ClipboardSnapshot::Basic {
+            text: clipboard.get().text().ok(),
+            html: clipboard.get().html().ok().filter(|html| !html.is_empty()),
+            image: clipboard.get().image().ok(),
+            files: clipboard
+                .get()
+                .file_list()
+                .ok()
+                .filter(|files| !files.is_empty()),
+        }
+    }
+
+    /// Puts the snapshot back. A basic snapshot restores only its richest
+    /// format: files, then HTML with its plain-text alternative, then an
+    /// image, then text, as arboard writes one kind of payload at a time.
+    pub(crate) fn restore(&self, clipboard: &mut Clipboard) -> Result<(), String> {
+        let restored = match self {
+            ClipboardSnapshot::Formats(formats) => platform::restore(formats),
+            ClipboardSnapshot::Basic {
+                text,
+                html,
+                image,
+                files,
+            } => {
+                let restored = if let Some(files) = files {
+                    clipboard.set().file_list(files)
+                } else if let Some(html) = html {
+                    clipboard.set().html(html.as_str(), text.as_deref())
+                } else if let Some(image) = image {
+                    clipboard.set().image(image.clone())
+                } else if let Some(text) = text {
+                    clipboard.set().text(text.as_str())
+                } else {
+                    clipboard.clear()
+                };
+                restored.map_err(|err| err.to_string())
+            }
+        };
+        restored.map_err(|err| format!("Failed to restore clipboard: {}", err))
+    }
+}
+
+/// X11 has no clipboard storage: the copying app serves each format on
+/// request. Capturing asks it for every target it lists; restoring takes
+/// the selection over and serves the captured targets from a thread until
+/// another app copies something.
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+    use x11rb::{
+        connection::{Connection, RequestConnection},
+        protocol::{
+            xproto::{
+                Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, CreateWindowAux,
+                EventMask, PropMode, Property, SelectionNotifyEvent, SelectionRequestEvent, Window,
+                WindowClass, SELECTION_NOTIFY_EVENT,
+            },
+            Event,
+        },
+        rust_connection::RustConnection,
+        wrapper::ConnectionExt as _,
+        COPY_DEPTH_FROM_PARENT, CURRENT_TIME, NONE,
+    };
+
+    /// How long the clipboard owner gets to answer each request.
+    const REPLY_TIMEOUT: Duration = Duration::from_millis(500);
+    const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+    x11rb::atom_manager! {
+        Atoms: AtomsCookie {
+            CLIPBOARD,
+            TARGETS,
+            MULTIPLE,
+            TIMESTAMP,
+            SAVE_TARGETS,
+            DELETE,
+            INCR,
+            GOBUDDY_CLIPBOARD,
+        }
+    }
+
+    impl Atoms {
+        /// Targets that describe the selection or ask its owner to do
+        /// something, rather than hold data.
+        fn is_data(&self, target: Atom) -> bool {
+            ![
+                self.TARGETS,
+                self.MULTIPLE,
+                self.TIMESTAMP,
+                self.SAVE_TARGETS,
+                self.DELETE,
+            ]
+            .contains(&target)
+        }
+    }
+
+    #[derive(Clone)]
+    struct Format {
+        target: Atom,
+        kind: Atom,
+        format: u8,
+        data: Vec<u8>,
+    }
+
+    /// Every data target the owner answered for.
+    #[derive(Clone)]
+    pub(crate) struct Formats(Vec<Format>);
+
+    /// A format too large for one request, sent a chunk at a time as the
+    /// requestor deletes the previous one.
+    struct Transfer {
+        requestor: Window,
+        property: Atom,
+        format: usize,
+        offset: usize,
+    }
+
+    struct Session {
+        conn: RustConnection,
+        window: Window,
+        atoms: Atoms,
+    }
+
+    impl Session {
+        fn open() -> Result<Self, String> {
+            let (conn, screen_num) = x11rb::connect(None).map_err(|err| err.to_string())?;
+            let screen = &conn.setup().roots[screen_num];
+            let window = conn.generate_id().map_err(|err| err.to_string())?;
+            conn.create_window(
+                COPY_DEPTH_FROM_PARENT,
+                window,
+                screen.root,
+                0,
+                0,
+                1,
+                1,
+                0,
+                WindowClass::INPUT_OUTPUT,
+                screen.root_visual,
+                &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+            )
+            .map_err(|err| err.to_string())?
+            .check()
+            .map_err(|err| err.to_string())?;
+            let atoms = Atoms::new(&conn)
+                .map_err(|err| err.to_string())?
+                .reply()
+                .map_err(|err| err.to_string())?;
+            Ok(Self {
+                conn,
+                window,
+                atoms,
+            })
+        }
+
+        /// Waits for the first event `wanted` accepts, dropping the others.
+        fn wait<T>(&self, mut wanted: impl FnMut(Event) -> Option<T>) -> Result<T, String> {
+            let deadline = Instant::now() + REPLY_TIMEOUT;
+            loop {
+                while let Some(event) = self.conn.poll_for_event().map_err(|err| err.to_string())? {
+                    if let Some(found) = wanted(event) {
+                        return Ok(found);
+                    }
+                }
+                if Instant::now() >= deadline {
+                    return Err("The clipboard owner didn't answer".into());
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+
+        /// Asks the owner for `target`. `None` when it refuses.
+        fn convert(&self, target: Atom) -> Result<Option<Format>, String> {
+            let property = self.atoms.GOBUDDY_CLIPBOARD;
+            self.conn
+                .convert_selection(
+                    self.window,
+                    self.atoms.CLIPBOARD,
+                    target,
+                    property,
+                    CURRENT_TIME,
+                )
+                .map_err(|err| err.to_string())?;
+            self.conn.flush().map_err(|err| err.to_string())?;
+            let answered = self.wait(|event| match event {
+                Event::SelectionNotify(notify)
+                    if notify.requestor == self.window && notify.target == target =>
+                {
+                    Some(notify.property != NONE)
+                }
+                _ => None,
+            })?;
+            if !answered {
+                return Ok(None);
+            }
+
+            // Reading with delete tells an INCR sender to start.
+            let reply = self.read_property(property)?;
+            if reply.kind == self.atoms.INCR {
+                return self.read_incremental(target).map(Some);
+            }
+            Ok(Some(Format { target, ..reply }))
+        }
+
+        fn read_incremental(&self, target: Atom) -> Result<Format, String> {
+            let property = self.atoms.GOBUDDY_CLIPBOARD;
+            let mut whole = Format {
+                target,
+                kind: NONE,
+                format: 8,
+                data: Vec::new(),
+            };
+            loop {
+                self.wait(|event| match event {
+                    Event::PropertyNotify(notify)
+                        if notify.window == self.window
+                            && notify.atom == property
+                            && notify.state == Property::NEW_VALUE =>
+                    {
+                        Some(())
+                    }
+                    _ => None,
+                })?;
+                let chunk = self.read_property(property)?;
+                if chunk.data.is_empty() {
+                    return Ok(whole);
+                }
+                whole.kind = chunk.kind;
+                whole.format = chunk.format;
+                whole.data.extend(chunk.data);
+            }
+        }
+
+        fn read_property(&self, property: Atom) -> Result<Format, String> {
+            let reply = self
+                .conn
+                .get_property(true, self.window, property, AtomEnum::ANY, 0, u32::MAX)
+                .map_err(|err| err.to_string())?
+                .reply()
+                .map_err(|err| err.to_string())?;
+            Ok(Format {
+                target: NONE,
+                kind: reply.type_,
+                format: reply.format,
+                data: reply.value,
+            })
+        }
+
+        /// Answers requests for `formats` until another client owns the
+        /// clipboard.
+        fn serve(self, formats: Formats) {
+            // A quarter of the largest request leaves room for the header;
+            // a multiple of four keeps 16- and 32-bit items whole.
+            let chunk_size = ((self.conn.maximum_request_bytes() / 4) & !3).max(4096);
+            let mut transfers: Vec<Transfer> = Vec::new();
+            loop {
+                let event = match self.conn.wait_for_event() {
+                    Ok(event) => event,
+                    Err(error) => {
+                        eprintln!("Stopped serving the restored clipboard: {}", error);
+                        return;
+                    }
+                };
+                let served = match event {
+                    Event::SelectionClear(_) => return,
+                    Event::SelectionRequest(request) => {
+                        self.answer(&request, &formats, chunk_size, &mut transfers)
+                    }
+                    Event::PropertyNotify(notify) if notify.state == Property::DELETE => {
+                        self.continue_transfer(&notify, &formats, chunk_size, &mut transfers)
+                    }
+                    _ => Ok(()),
+                };
+                if let Err(error) =
+                    served.and_then(|_| self.conn.flush().map_err(|err| err.to_string()))
+                {
+                    eprintln!("Failed to serve the restored clipboard: {}", error);
+                }
+            }
+        }
+
+        fn answer(
+            &self,
+            request: &SelectionRequestEvent,
+            formats: &Formats,
+            chunk_size: usize,
+            transfers: &mut Vec<Transfer>,
+        ) -> Result<(), String> {
+            // Obsolete clients leave the property out and mean the target.
+            let property = match request.property {
+                NONE => request.target,
+                property => property,
+            };
+            let position = formats
+                .0
+                .iter()
+                .position(|format| format.target == request.target);
+            let served = if request.target == self.atoms.TARGETS {
+                let targets: Vec<Atom> = [self.atoms.TARGETS]
+                    .into_iter()
+                    .chain(formats.0.iter().map(|format| format.target))
+                    .collect();
+                self.conn
+                    .change_property32(
+                        PropMode::REPLACE,
+                        request.requestor,
+                        property,
+                        AtomEnum::ATOM,
+                        &targets,
+                    )
+                    .map_err(|err| err.to_string())?;
+                true
+            } else if let Some(position) = position {
+                let format = &formats.0[position];
+                if format.data.len() > chunk_size {
+                    self.conn
+                        .change_window_attributes(
+                            request.requestor,
+                            &ChangeWindowAttributesAux::new()
+                                .event_mask(EventMask::PROPERTY_CHANGE),
+                        )
+                        .map_err(|err| err.to_string())?;
+                    self.conn
+                        .change_property32(
+                            PropMode::REPLACE,
+                            request.requestor,
+                            property,
+                            self.atoms.INCR,
+                            &[format.data.len() as u32],
+                        )
+                        .map_err(|err| err.to_string())?;
+                    transfers.push(Transfer {
+                        requestor: request.requestor,
+                        property,
+                        format: position,
+                        offset: 0,
+                    });
+                } else {
+                    self.put(request.requestor, property, format, &format.data)?;
+                }
+                true
+            } else {
+                false
+            };
+
+            let notify = SelectionNotifyEvent {
+                response_type: SELECTION_NOTIFY_EVENT,
+                sequence: 0,
+                time: request.time,
+                requestor: request.requestor,
+                selection: request.selection,
+                target: request.target,
+                property: if served { property } else { NONE },
+            };
+            self.conn
+                .send_event(false, request.requestor, EventMask::NO_EVENT, notify)
+                .map_err(|err| err.to_string())?;
+            Ok(())
+        }
+
+        /// Sends the next chunk once the requestor has taken the last one;
+        /// an empty chunk ends the transfer.
+        fn continue_transfer(
+            &self,
+            notify: &x11rb::protocol::xproto::PropertyNotifyEvent,
+            formats: &Formats,
+            chunk_size: usize,
+            transfers: &mut Vec<Transfer>,
+        ) -> Result<(), String> {
+            let Some(index) = transfers.iter().position(|transfer| {
+                transfer.requestor == notify.window && transfer.property == notify.atom
+            }) else {
+                return Ok(());
+            };
+            let transfer = &mut transfers[index];
+            let format = &formats.0[transfer.format];
+            let end = (transfer.offset + chunk_size).min(format.data.len());
+            let chunk = &format.data[transfer.offset..end];
+            transfer.offset = end;
+            let finished = chunk.is_empty();
+            self.put(transfer.requestor, transfer.property, format, chunk)?;
+            if finished {
+                transfers.remove(index);
+            }
+            Ok(())
+        }
+
+        fn put(
+            &self,
+            requestor: Window,
+            property: Atom,
+            format: &Format,
+            data: &[u8],
+        ) -> Result<(), String> {
+            let unit = usize::from(format.format / 8).max(1);
+            self.conn
+                .change_property(
+                    PropMode::REPLACE,
+                    requestor,
+                    property,
+                    format.kind,
+                    format.format,
+                    (data.len() / unit) as u32,
+                    data,
+                )
+                .map_err(|err| err.to_string())?;
+            Ok(())
+        }
+    }
+
+    pub(super) fn capture() -> Result<Formats, String> {
+        let session = Session::open()?;
+        let owner = session
+            .conn
+            .get_selection_owner(session.atoms.CLIPBOARD)
+            .map_err(|err| err.to_string())?
+            .reply()
+            .map_err(|err| err.to_string())?
+            .owner;
+        if owner == NONE {
+            return Ok(Formats(Vec::new()));
+        }
+
+        let targets = session
+            .convert(session.atoms.TARGETS)?
+            .ok_or("The clipboard owner doesn't list its formats")?;
+        let mut formats = Vec::new();
+        for target in targets
+            .data
+            .chunks_exact(4)
+            .map(|bytes| Atom::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .filter(|target| session.atoms.is_data(*target))
+        {
+            // One format failing shouldn't cost the others.
+            match session.convert(target) {
+                Ok(Some(format)) => formats.push(format),
+                Ok(None) => {}
+                Err(error) => eprintln!("Skipped a clipboard format: {}", error),
+            }
+        }
+        Ok(Formats(formats))
+    }
+
+    pub(super) fn restore(formats: &Formats) -> Result<(), String> {
+        let session = Session::open()?;
+        let owner = if formats.0.is_empty() {
+            NONE
+        } else {
+            session.window
+        };
+        session
+            .conn
+            .set_selection_owner(owner, session.atoms.CLIPBOARD, CURRENT_TIME)
+            .map_err(|err| err.to_string())?;
+        if owner == NONE {
+            return session.conn.flush().map_err(|err| err.to_string());
+        }
+        let current = session
+            .conn
+            .get_selection_owner(session.atoms.CLIPBOARD)
+            .map_err(|err| err.to_string())?
+            .reply()
+            .map_err(|err| err.to_string())?
+            .owner;
+        if current != owner {
+            return Err("Another app took the clipboard first".into());
+        }
+        let formats = formats.clone();
+        thread::spawn(move || session.serve(formats));
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc2::runtime::ProtocolObject;
+    use objc2_app_kit::{NSPasteboard, NSPasteboardItem, NSPasteboardWriting};
+    use objc2_foundation::{NSArray, NSData, NSString};
+
+    /// Each pasteboard item with the data of every type it carries.
+    pub(crate) struct Formats(Vec<Vec<(String, Vec<u8>)>>);
+
+    pub(super) fn capture() -> Result<Formats, String> {
+        let pasteboard = NSPasteboard::generalPasteboard();
+        let items = pasteboard
+            .pasteboardItems()
+            .map(|items| items.to_vec())
+            .unwrap_or_default();
+        Ok(Formats(
+            items
+                .iter()
+                .map(|item| {
+                    item.types()
+                        .to_vec()
+                        .iter()
+                        .filter_map(|kind| {
+                            Some((kind.to_string(), item.dataForType(kind)?.to_vec()))
+                        })
+                        .collect()
+                })
+                .collect(),
+        ))
+    }
+
+    pub(super) fn restore(formats: &Formats) -> Result<(), String> {
+        let pasteboard = NSPasteboard::generalPasteboard();
+        pasteboard.clearContents();
+        if formats.0.is_empty() {
+            return Ok(());
+        }
+        let items: Vec<_> = formats
+            .0
+            .iter()
+            .map(|types| {
+                let item = NSPasteboardItem::new();
+                for (kind, data) in types {
+                    item.setData_forType(&NSData::with_bytes(data), &NSString::from_str(kind));
+                }
+                item
+            })
+            .collect();
+        let writers: Vec<&ProtocolObject<dyn NSPasteboardWriting>> = items
+            .iter()
+            .map(|item| ProtocolObject::from_ref(&**item))
+            .collect();
+        if pasteboard.writeObjects(&NSArray::from_slice(&writers)) {
+            Ok(())
+        } else {
+            Err("The pasteboard refused the items".into())
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::{io, ptr, thread, time::Duration};
+    use windows_sys::Win32::{
+        Foundation::GlobalFree,
+        System::{
+            DataExchange::{
+                CloseClipboard, EmptyClipboard, EnumClipboardFormats, GetClipboardData,
+                OpenClipboard, SetClipboardData,
+            },
+            Memory::{GlobalAlloc, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE},
+            Ole::{
+                CF_BITMAP, CF_DSPBITMAP, CF_DSPENHMETAFILE, CF_DSPMETAFILEPICT, CF_ENHMETAFILE,
+                CF_GDIOBJLAST, CF_METAFILEPICT, CF_OWNERDISPLAY, CF_PALETTE, CF_PRIVATEFIRST,
+            },
+        },
+    };
+
+    // Another app may have the clipboard open for a moment.
+    const OPEN_ATTEMPTS: usize = 10;
+    const OPEN_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+    /// Every format held in global memory, by format id.
+    pub(crate) struct Formats(Vec<(u32, Vec<u8>)>);
+
+    /// Keeps the clipboard open, closing it on drop.
+    struct OpenClipboardGuard;
+
+    impl OpenClipboardGuard {
+        fn open() -> Result<Self, String> {
+            for _ in 0..OPEN_ATTEMPTS {
+                if unsafe { OpenClipboard(ptr::null_mut()) } != 0 {
+                    return Ok(Self);
+                }
+                thread::sleep(OPEN_RETRY_DELAY);
+            }
+            Err(format!(
+                "Failed to open the clipboard: {}",
+                io::Error::last_os_error()
+            ))
+        }
+    }
+
+    impl Drop for OpenClipboardGuard {
+        fn drop(&mut self) {
+            unsafe {
+                CloseClipboard();
+            }
+        }
+    }
+
+    /// Whether `format` holds a global memory block. The others hold GDI
+    /// handles or are drawn by their owner; Windows synthesizes bitmaps and
+    /// metafiles from the DIB and other formats that do come back.
+    fn is_memory_format(format: u32) -> bool {
+        let handles = [
+            CF_BITMAP,
+            CF_METAFILEPICT,
+            CF_PALETTE,
+            CF_ENHMETAFILE,
+            CF_OWNERDISPLAY,
+            CF_DSPBITMAP,
+            CF_DSPMETAFILEPICT,
+            CF_DSPENHMETAFILE,
+        ];
+        !handles.iter().any(|handle| u32::from(*handle) == format)
+            && !(u32::from(CF_PRIVATEFIRST)..=u32::from(CF_GDIOBJLAST)).contains(&format)
+    }
+
+    pub(super) fn capture() -> Result<Formats, String> {
+        let _open = OpenClipboardGuard::open()?;
+        let mut formats = Vec::new();
+        let mut format = 0;
+        loop {
+            format = unsafe { EnumClipboardFormats(format) };
+            if format == 0 {
+                break;
+            }
+            if !is_memory_format(format) {
+                continue;
+            }
+            unsafe {
+                let handle = GetClipboardData(format);
+                if handle.is_null() {
+                    continue;
+                }
+                let source = GlobalLock(handle);
+                if source.is_null() {
+                    continue;
+                }
+                let data = std::slice::from_raw_parts(source.cast::<u8>(), GlobalSize(handle));
+                formats.push((format, data.to_vec()));
+                GlobalUnlock(handle);
+            }
+        }
+        Ok(Formats(formats))
+    }
+
+    pub(super) fn restore(formats: &Formats) -> Result<(), String> {
+        let _open = OpenClipboardGuard::open()?;
+        if unsafe { EmptyClipboard() } == 0 {
+            return Err(io::Error::last_os_error().to_string());
+        }
+        for (format, data) in &formats.0 {
+            unsafe {
+                let handle = GlobalAlloc(GMEM_MOVEABLE, data.len().max(1));
+                if handle.is_null() {
+                    return Err(io::Error::last_os_error().to_string());
+                }
+                let target = GlobalLock(handle);
+                if target.is_null() {
+                    GlobalFree(handle);
+                    return Err(io::Error::last_os_error().to_string());
+                }
+                ptr::copy_nonoverlapping(data.as_ptr(), target.cast::<u8>(), data.len());
+                GlobalUnlock(handle);
+                // The clipboard owns the memory once it accepts it.
+                if SetClipboardData(*format, handle).is_null() {
+                    GlobalFree(handle);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+mod platform {
+    pub(crate) struct Formats;
+
+    pub(super) fn capture() -> Result<Formats, String> {
+        Err("not supported on this platform".into())
+    }
+
+    pub(super) fn restore(_formats: &Formats) -> Result<(), String> {
+        Err("not supported on this platform".into())
+    }
+}
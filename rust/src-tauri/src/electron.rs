@@ -0,0 +1,386 @@
+//! Imports the configuration of the Electron build of GoBuddy. It kept one
+//! JSON file per section in its `userData` folder, each with a list of
+//! `profiles` and a `general.hotkey`, plus a `config.json` with the OpenAI
+//! key and the launch-at-login switch.
+//!
+//! Importing only adds: profiles become new presets, and hotkeys and settings
+//! are taken only where GoBuddy has none of its own. Whatever is left out is
+//! listed in the report with the reason.
+
+use crate::{hotkeys, library::ImportReport, PersistedState, Preset, PresetView, SettingsState};
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const INPUT_FIELD_FILE: &str = "inputfield-config.json";
+const SELECTION_FILE: &str = "selection-config.json";
+const APP_CONFIG_FILE: &str = "config.json";
+
+// Electron names `userData` after the product, or after the package in
+// development builds.
+const USER_DATA_DIRS: [&str; 2] = ["GoBuddy", "gobuddy"];
+
+/// `inputfield-config.json` and `selection-config.json`.
+#[derive(Debug, Default, Deserialize)]
+struct SectionConfig {
+    #[serde(default)]
+    profiles: Vec<Profile>,
+    #[serde(default)]
+    general: Option<GeneralConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Profile {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    prompt: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeneralConfig {
+    /// Cleared to null when Electron failed to register it.
+    #[serde(default)]
+    hotkey: Option<String>,
+}
+
+/// `config.json`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AppConfig {
+    #[serde(default)]
+    api_key: Option<String>,
+    #[serde(default)]
+    auto_start: Option<bool>,
+}
+
+#[derive(Debug, Default)]
+struct ElectronConfig {
+    input_field: Option<SectionConfig>,
+    selection: Option<SectionConfig>,
+    app: Option<AppConfig>,
+}
+
+/// The Electron config files in the first `userData` folder under
+/// `config_dir` (the platform's app data folder) that has any.
+pub(crate) fn find_config_files(config_dir: &Path) -> Vec<PathBuf> {
+    USER_DATA_DIRS
+        .iter()
+        .map(|name| {
+            [INPUT_FIELD_FILE, SELECTION_FILE, APP_CONFIG_FILE]
+                .iter()
+                .map(|file| config_dir.join(name).join(file))
+                .filter(|path| path.is_file())
+                .collect::<Vec<_>>()
+        })
+        .find(|files| !files.is_empty())
+        .unwrap_or_default()
+}
+
+/// Reads `paths` and merges them into `state`. Files are recognised by
+/// name, as Electron wrote them.
+pub(crate) fn import(state: &mut PersistedState, paths: &[PathBuf]) -> ImportReport {
+    let mut report = ImportReport::default();
+    let config = read_config(paths, &mut report);
+    merge(state, config, &mut report);
+    report
+}
+
+fn read_config(paths: &[PathBuf], report: &mut ImportReport) -> ElectronConfig {
+    fn parse<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T, String> {
+        let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        serde_json::from_str(&contents).map_err(|err| err.to_string())
+    }
+
+    let mut config = ElectronConfig::default();
+    for path in paths {
+        let display = path.display().to_string();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let parsed = match name.as_str() {
+            INPUT_FIELD_FILE => parse(path).map(|section| config.input_field = Some(section)),
+            SELECTION_FILE => parse(path).map(|section| config.selection = Some(section)),
+            APP_CONFIG_FILE => parse(path).map(|app| config.app = Some(app)),
+            _ => Err(format!(
+                "not an Electron GoBuddy file; expected {}, {} or {}",
+                INPUT_FIELD_FILE, SELECTION_FILE, APP_CONFIG_FILE
+            )),
+        };
+        match parsed {
+            Ok(()) => report.sources.push(display),
+            Err(reason) => report.skip(display, reason),
+        }
+    }
+    config
+}
+
+fn merge(state: &mut PersistedState, config: ElectronConfig, report: &mut ImportReport) {
+    for (view, section) in [
+        (PresetView::InputField, config.input_field),
+        (PresetView::Selection, config.selection),
+    ] {
+        let Some(section) = section else {
+            continue;
+        };
+        merge_profiles(state, view, section.profiles, report);
+        if let Some(hotkey) = section.general.and_then(|general| general.hotkey) {
+            merge_hotkey(state, view, &hotkey, report);
+        }
+    }
+    if let Some(app) = config.app {
+        merge_settings(state, app, report);
+    }
+}
+
+fn merge_profiles(
+    state: &mut PersistedState,
+    view: PresetView,
+    profiles: Vec<Profile>,
+    report: &mut ImportReport,
+) {
+    for profile in profiles {
+        let name = profile.name.trim().to_string();
+        let item = if name.is_empty() {
+            format!("{} preset without a name", view.label())
+        } else {
+            format!("{} preset \"{}\"", view.label(), name)
+        };
+        if name.is_empty() && profile.prompt.trim().is_empty() {
+            report.skip(item, "it has no name or prompt");
+            continue;
+        }
+        let presets = state.presets.for_view_mut(view);
+        if presets
+            .iter()
+            .any(|preset| preset.name == name && preset.prompt == profile.prompt)
+        {
+            report.skip(item, "a preset with the same name and prompt exists");
+            continue;
+        }
+
+        let id = format!("preset-{}", state.next_preset_id);
+        state.next_preset_id += 1;
+        presets.push(Preset {
+            id: id.clone(),
+            name: if name.is_empty() { id.clone() } else { name },
+            prompt: profile.prompt,
+            ..Default::default()
+        });
+        let active = state.active_preset_ids.for_view_mut(view);
+        if active.is_none() {
+            *active = Some(id);
+        }
+        report.imported.push(item);
+    }
+}
+
+fn merge_hotkey(
+    state: &mut PersistedState,
+    view: PresetView,
+    electron_hotkey: &str,
+    report: &mut ImportReport,
+) {
+    // Electron stored "Ctrl+Shift+K"; the UI shows "Ctrl + Shift + K".
+    let display = electron_hotkey
+        .split('+')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" + ");
+    let item = format!("{} hotkey {}", view.label(), display);
+    if display.is_empty() {
+        return;
+    }
+
+    let errors_before = hotkeys::plan(state).errors.len();
+    let mut hotkeys_state = state.hotkeys.clone().unwrap_or_default();
+    let slot = match view {
+        PresetView::InputField => &mut hotkeys_state.input_field,
+        PresetView::Selection => &mut hotkeys_state.selection,
+        PresetView::Screenshot => &mut hotkeys_state.screenshot,
+    };
+    if !slot.trim().is_empty() {
+        report.skip(item, format!("GoBuddy already uses {}", slot));
+        return;
+    }
+    *slot = display;
+
+    let previous = state.hotkeys.replace(hotkeys_state);
+    let plan = hotkeys::plan(state);
+    if plan.errors.len() > errors_before {
+        let reason = plan
+            .errors
+            .last()
+            .map(|error| error.message().to_string())
+            .unwrap_or_default();
+        state.hotkeys = previous;
+        report.skip(item, reason);
+        return;
+    }
+    report.imported.push(item);
+}
+
+fn merge_settings(state: &mut PersistedState, app: AppConfig, report: &mut ImportReport) {
+    let fresh = state.settings.is_none();
+    let settings = state.settings.get_or_insert_with(SettingsState::default);
+
+    if let Some(api_key) = app.api_key.map(|key| key.trim().to_string()) {
+        if !api_key.is_empty() {
+            if settings.openai_api_key.trim().is_empty() {
+                settings.openai_api_key = api_key;
+                report.imported.push("OpenAI API key".into());
+            } else {
+                report.skip("OpenAI API key", "GoBuddy already has one");
+            }
+        }
+    }
+    if let Some(auto_start) = app.auto_start {
+        let item = format!(
+            "Launch at login ({})",
+            if auto_start { "on" } else { "off" }
+        );
+        if fresh {
+            settings.auto_open_on_start = auto_start;
+            report.imported.push(item);
+        } else {
+            report.skip(item, "GoBuddy's own setting is kept");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HotkeysState;
+
+    fn write_fixture(test: &str, files: &[(&str, &str)]) -> Vec<PathBuf> {
+        let dir =
+            std::env::temp_dir().join(format!("gobuddy-electron-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        let user_data = dir.join("GoBuddy");
+        fs::create_dir_all(&user_data).unwrap();
+        for (name, contents) in files {
+            fs::write(user_data.join(name), contents).unwrap();
+        }
+        find_config_files(&dir)
+    }
+
+    const INPUT_FIELD: &str = r#"{
+        "profiles": [
+            { "id": "default-editgrammar", "name": "EditGrammar", "prompt": "Fix the grammar:" },
+            { "id": "1718000000000", "name": "Shorten", "prompt": "Make this shorter:" },
+            { "id": "1718000000001", "name": "", "prompt": "" }
+        ],
+        "general": { "hotkey": "Ctrl+Q" }
+    }"#;
+    const SELECTION: &str = r#"{
+        "profiles": [
+            { "id": "default-translate", "name": "TranslateToEnglish", "prompt": "Translate:" }
+        ],
+        "general": { "hotkey": "Ctrl+E" }
+    }"#;
+    const APP_CONFIG: &str =
+        r#"{ "autoStart": true, "apiKey": "sk-electron", "hasSeenTutorial": true }"#;
+
+    #[test]
+    fn profiles_hotkeys_and_settings_are_imported() {
+        let paths = write_fixture(
+            "full",
+            &[
+                (INPUT_FIELD_FILE, INPUT_FIELD),
+                (SELECTION_FILE, SELECTION),
+                (APP_CONFIG_FILE, APP_CONFIG),
+            ],
+        );
+        assert_eq!(paths.len(), 3);
+        let mut state = PersistedState {
+            next_preset_id: 4,
+            ..Default::default()
+        };
+
+        let report = import(&mut state, &paths);
+        assert_eq!(report.sources.len(), 3);
+        assert_eq!(
+            report.imported,
+            vec![
+                "Input Field preset \"EditGrammar\"",
+                "Input Field preset \"Shorten\"",
+                "Input Field hotkey Ctrl + Q",
+                "Selection preset \"TranslateToEnglish\"",
+                "Selection hotkey Ctrl + E",
+                "OpenAI API key",
+                "Launch at login (on)",
+            ]
+        );
+        assert_eq!(report.skipped.len(), 1);
+        let ids: Vec<_> = state
+            .presets
+            .input_field
+            .iter()
+            .map(|preset| preset.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["preset-4", "preset-5"]);
+        assert_eq!(state.next_preset_id, 7);
+        assert_eq!(
+            state.active_preset_ids.input_field.as_deref(),
+            Some("preset-4")
+        );
+        let settings = state.settings.clone().unwrap();
+        assert_eq!(settings.openai_api_key, "sk-electron");
+        assert!(settings.auto_open_on_start);
+
+        // Importing again changes nothing.
+        let again = import(&mut state, &paths);
+        assert!(again.imported.is_empty(), "{:?}", again.imported);
+        fs::remove_dir_all(paths[0].parent().unwrap().parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn existing_settings_win_and_bad_files_are_reported() {
+        let paths = write_fixture(
+            "conflicts",
+            &[
+                (INPUT_FIELD_FILE, INPUT_FIELD),
+                (SELECTION_FILE, "{ \"profiles\": ["),
+                (APP_CONFIG_FILE, APP_CONFIG),
+            ],
+        );
+        let mut state = PersistedState {
+            settings: Some(SettingsState {
+                openai_api_key: "sk-mine".into(),
+                ..Default::default()
+            }),
+            hotkeys: Some(HotkeysState {
+                screenshot: "Ctrl+Q".into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let report = import(&mut state, &paths);
+        let skipped: Vec<_> = report
+            .skipped
+            .iter()
+            .map(|skipped| skipped.item.as_str())
+            .collect();
+        assert!(skipped[0].ends_with(SELECTION_FILE), "{:?}", skipped);
+        assert_eq!(
+            &skipped[1..],
+            [
+                "Input Field preset without a name",
+                "Input Field hotkey Ctrl + Q",
+                "OpenAI API key",
+                "Launch at login (on)",
+            ]
+        );
+        assert!(report.skipped[2].reason.contains("Ctrl+Q"));
+        let hotkeys = state.hotkeys.unwrap();
+        assert_eq!(hotkeys.input_field, "");
+        assert_eq!(state.settings.unwrap().openai_api_key, "sk-mine");
+        fs::remove_dir_all(paths[0].parent().unwrap().parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,118 @@
+//! Identifies the app that currently has keyboard focus, so output can be
+//! tailored to (and history attributed to) the app being written into.
+
+/// Name of the focused app: the WM_CLASS class on X11, the localized app name
+/// on macOS and the executable stem on Windows. `None` when it can't be told.
+pub(crate) fn focused_app() -> Option<String> {
+    platform::focused_app()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use x11rb::{
+        connection::Connection,
+        protocol::xproto::{AtomEnum, ConnectionExt},
+    };
+
+    pub(super) fn focused_app() -> Option<String> {
+        let (conn, screen_num) = x11rb::connect(None).ok()?;
+        let root = conn.setup().roots.get(screen_num)?.root;
+        let active_atom = conn
+            .intern_atom(false, b"_NET_ACTIVE_WINDOW")
+            .ok()?
+            .reply()
+            .ok()?
+            .atom;
+        let window = conn
+            .get_property(false, root, active_atom, AtomEnum::WINDOW, 0, 1)
+            .ok()?
+            .reply()
+            .ok()?
+            .value32()?
+            .next()
+            .filter(|window| *window != 0)?;
+        let class = conn
+            .get_property(false, window, AtomEnum::WM_CLASS, AtomEnum::STRING, 0, 256)
+            .ok()?
+            .reply()
+            .ok()?;
+
+        // WM_CLASS holds "instance\0class\0"; the class is the stable app name.
+        let mut parts = class
+            .value
+            .split(|byte| *byte == 0)
+            .filter(|part| !part.is_empty());
+        let instance = parts.next()?;
+        let class_name = parts.next().unwrap_or(instance);
+        Some(String::from_utf8_lossy(class_name).into_owned())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc2_app_kit::NSWorkspace;
+
+    pub(super) fn focused_app() -> Option<String> {
+        let workspace = NSWorkspace::sharedWorkspace();
+        let app = workspace.frontmostApplication()?;
+        Some(app.localizedName()?.to_string())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::{os::windows::ffi::OsStringExt, path::Path};
+    use windows_sys::Win32::{
+        Foundation::CloseHandle,
+        System::Threading::{
+            OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+            PROCESS_QUERY_LIMITED_INFORMATION,
+        },
+        UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId},
+    };
+
+    pub(super) fn focused_app() -> Option<String> {
+        unsafe {
+            let window = GetForegroundWindow();
+            if window.is_null() {
+                return None;
+            }
+            let mut process_id = 0u32;
+            GetWindowThreadProcessId(window, &mut process_id);
+            if process_id == 0 {
+                return None;
+            }
+
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, process_id);
+            if process.is_null() {
+                return None;
+            }
+            let mut buffer = [0u16; 1024];
+            let mut len = buffer.len() as u32;
+            let ok = QueryFullProcessImageNameW(
+                process,
+                PROCESS_NAME_WIN32,
+                buffer.as_mut_ptr(),
+                &mut len,
+            );
+            CloseHandle(process);
+            if ok == 0 {
+                return None;
+            }
+
+            let path = std::ffi::OsString::from_wide(&buffer[..len as usize]);
+            Path::new(&path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+mod platform {
+    pub(super) fn focused_app() -> Option<String> {
+        None
+    }
+}
@@ -0,0 +1,859 @@
+//! The pointer and keyboard gestures behind the selection overlay: a drag, a
+//! double or triple click, or a keyboard selection (Shift+navigation keys,
+//! select all) shows the overlay bubble, clicking the bubble opens the
+//! floating panel, and clicking elsewhere or typing dismisses both.
+//!
+//! `GestureTracker` only decides; the rdev listener thread feeds it events
+//! and carries out the returned actions on the windows.
+
+use crate::chords::Modifiers;
+use rdev::{Button, EventType, Key};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+// Allowed ranges for `BehaviorSettings`. Sizes and offsets are in logical
+// pixels; the drag threshold is in the listener's cursor coordinates.
+const MULTI_CLICK_MS_RANGE: (u64, u64) = (100, 2_000);
+const DRAG_THRESHOLD_RANGE: (f64, f64) = (1.0, 50.0);
+const OVERLAY_SIZE_RANGE: (f64, f64) = (16.0, 128.0);
+const OVERLAY_OFFSET_RANGE: (f64, f64) = (-300.0, 300.0);
+const PANEL_WIDTH_RANGE: (f64, f64) = (80.0, 800.0);
+const PANEL_HEIGHT_RANGE: (f64, f64) = (80.0, 1_200.0);
+
+/// Gesture and placement tuning, persisted as the "behavior" section of the
+/// state file. Saving rejects values outside their ranges; values read from
+/// a hand-edited file are clamped instead.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct BehaviorSettings {
+    /// Longest gap between the clicks of a double or triple click.
+    pub(crate) multi_click_ms: u64,
+    /// Pointer travel between press and release that counts as a drag, and
+    /// the most the pointer may wander between the clicks of a double click.
+    pub(crate) drag_threshold: f64,
+    /// Side of the square overlay bubble.
+    pub(crate) overlay_size: f64,
+    /// Horizontal offset of the bubble from where a rightward drag ended.
+    pub(crate) overlay_offset_right: f64,
+    /// Horizontal offset of the bubble from where a leftward drag ended.
+    pub(crate) overlay_offset_left: f64,
+    /// Vertical offset of the bubble from where the selection ended.
+    pub(crate) overlay_offset_y: f64,
+    pub(crate) panel_width: f64,
+    pub(crate) panel_height: f64,
+}
+
+impl Default for BehaviorSettings {
+    fn default() -> Self {
+        Self {
+            multi_click_ms: 500,
+            drag_threshold: 5.0,
+            overlay_size: 32.0,
+            overlay_offset_right: 10.0,
+            overlay_offset_left: -50.0,
+            overlay_offset_y: 10.0,
+            panel_width: 120.0,
+            panel_height: 180.0,
+        }
+    }
+}
+
+impl BehaviorSettings {
+    /// One message per value outside its range.
+    pub(crate) fn validate(&self) -> Result<(), Vec<String>> {
+        let (min_click, max_click) = MULTI_CLICK_MS_RANGE;
+        let checks = [
+            (
+                "Double-click window",
+                self.multi_click_ms as f64,
+                (min_click as f64, max_click as f64),
+            ),
+            ("Drag threshold", self.drag_threshold, DRAG_THRESHOLD_RANGE),
+            ("Bubble size", self.overlay_size, OVERLAY_SIZE_RANGE),
+            (
+                "Bubble offset after a rightward selection",
+                self.overlay_offset_right,
+                OVERLAY_OFFSET_RANGE,
+            ),
+            (
+                "Bubble offset after a leftward selection",
+                self.overlay_offset_left,
+                OVERLAY_OFFSET_RANGE,
+            ),
+            (
+                "Vertical bubble offset",
+                self.overlay_offset_y,
+                OVERLAY_OFFSET_RANGE,
+            ),
+            ("Panel width", self.panel_width, PANEL_WIDTH_RANGE),
+            ("Panel height", self.panel_height, PANEL_HEIGHT_RANGE),
+        ];
+        let errors: Vec<String> = checks
+            .into_iter()
+            .filter(|(_, value, (min, max))| !(*min..=*max).contains(value))
+            .map(|(label, _, (min, max))| format!("{} must be between {} and {}", label, min, max))
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// A copy with every value pulled into its range.
+    pub(crate) fn clamped(&self) -> Self {
+        let clamp = |value: f64, (min, max): (f64, f64)| value.clamp(min, max);
+        Self {
+            multi_click_ms: self
+                .multi_click_ms
+                .clamp(MULTI_CLICK_MS_RANGE.0, MULTI_CLICK_MS_RANGE.1),
+            drag_threshold: clamp(self.drag_threshold, DRAG_THRESHOLD_RANGE),
+            overlay_size: clamp(self.overlay_size, OVERLAY_SIZE_RANGE),
+            overlay_offset_right: clamp(self.overlay_offset_right, OVERLAY_OFFSET_RANGE),
+            overlay_offset_left: clamp(self.overlay_offset_left, OVERLAY_OFFSET_RANGE),
+            overlay_offset_y: clamp(self.overlay_offset_y, OVERLAY_OFFSET_RANGE),
+            panel_width: clamp(self.panel_width, PANEL_WIDTH_RANGE),
+            panel_height: clamp(self.panel_height, PANEL_HEIGHT_RANGE),
+        }
+    }
+
+    /// Top-left corner of the overlay bubble for a selection that ended at
+    /// `(x, y)`, before clamping to the screen. The bubble sits on the side
+    /// the drag went towards, so it doesn't cover the selection.
+    pub(crate) fn overlay_origin(&self, x: f64, y: f64, start_x: f64) -> (f64, f64) {
+        let x_offset = if x < start_x {
+            self.overlay_offset_left
+        } else {
+            self.overlay_offset_right
+        };
+        (x + x_offset, y + self.overlay_offset_y)
+    }
+
+    /// Top-left corner of the floating panel opened from the bubble at
+    /// `overlay`, before clamping: centred under the bubble.
+    pub(crate) fn panel_origin(&self, (overlay_x, overlay_y): (f64, f64)) -> (f64, f64) {
+        (
+            overlay_x + self.overlay_size / 2.0 - self.panel_width / 2.0,
+            overlay_y,
+        )
+    }
+}
+
+/// The overlay and floating panel as the listener last saw them.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct WindowState {
+    pub(crate) overlay_visible: bool,
+    /// `(x, y, width, height)` of the overlay bubble.
+    pub(crate) overlay_bounds: Option<(f64, f64, f64, f64)>,
+    pub(crate) floating_visible: bool,
+    /// `(x, y, width, height)` of the floating panel.
+    pub(crate) floating_bounds: Option<(f64, f64, f64, f64)>,
+}
+
+impl WindowState {
+    fn inside_overlay(&self, (x, y): (f64, f64)) -> bool {
+        match self.overlay_bounds {
+            Some((ox, oy, ow, oh)) if self.overlay_visible => {
+                x >= ox && x <= ox + ow && y >= oy && y <= oy + oh
+            }
+            _ => false,
+        }
+    }
+
+    fn inside_floating(&self, (x, y): (f64, f64)) -> bool {
+        match self.floating_bounds {
+            Some((fx, fy, fw, fh)) if self.floating_visible => {
+                x >= fx && x <= fx + fw && y >= fy && y <= fy + fh
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum GestureAction {
+    /// Shows the overlay bubble next to `(x, y)`, on the side the drag went
+    /// towards from `start_x`.
+    ShowOverlay {
+        x: f64,
+        y: f64,
+        start_x: f64,
+    },
+    HideOverlay,
+    HideFloating,
+    /// Opens the floating panel at the overlay bubble, then hides the bubble.
+    OpenPanel,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct GestureTracker {
+    behavior: BehaviorSettings,
+    cursor: (f64, f64),
+    /// Where the left button went down, while it is held.
+    press_start: Option<(f64, f64)>,
+    moved: bool,
+    /// The left button went down on the overlay bubble.
+    overlay_click: bool,
+    /// Time and place of the last left press, for telling multi-clicks.
+    last_press: Option<(Instant, (f64, f64))>,
+    /// Clicks in the current streak: 2 for a double click, 3 for a triple.
+    click_count: u32,
+    modifiers: Modifiers,
+    /// The last key pressed extended a selection; the bubble shows once the
+    /// modifiers are let go.
+    keyboard_selection: bool,
+}
+
+impl GestureTracker {
+    pub(crate) fn set_behavior(&mut self, behavior: BehaviorSettings) {
+        self.behavior = behavior.clamped();
+    }
+
+    /// `at` is when the event happened; it only matters for multi-clicks.
+    pub(crate) fn handle(
+        &mut self,
+        event: &EventType,
+        at: Instant,
+        windows: &WindowState,
+    ) -> Vec<GestureAction> {
+        match *event {
+            EventType::ButtonPress(Button::Left) => self.press(at, windows),
+            EventType::ButtonRelease(Button::Left) => self.release(windows),
+            EventType::MouseMove { x, y } => {
+                self.cursor = (x, y);
+                if let Some(start) = self.press_start {
+                    if distance(start, self.cursor) > self.behavior.drag_threshold {
+                        self.moved = true;
+                    }
+                }
+                Vec::new()
+            }
+            EventType::KeyPress(key) => self.key_press(key),
+            EventType::KeyRelease(key) => self.key_release(key, windows),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Any key dismisses both windows, including those that go on to make a
+    /// keyboard selection.
+    fn key_press(&mut self, key: Key) -> Vec<GestureAction> {
+        self.overlay_click = false;
+        if !self.modifiers.update(key, true) {
+            self.keyboard_selection = self.extends_selection(key);
+        }
+        vec![GestureAction::HideOverlay, GestureAction::HideFloating]
+    }
+
+    /// Shows the bubble at the pointer once the last modifier of a keyboard
+    /// selection is let go. The caret position isn't available portably,
+    /// so the pointer stands in for it.
+    fn key_release(&mut self, key: Key, windows: &WindowState) -> Vec<GestureAction> {
+        if !self.modifiers.update(key, false) {
+            return Vec::new();
+        }
+        let Modifiers {
+            ctrl, shift, meta, ..
+        } = self.modifiers;
+        if !self.keyboard_selection || ctrl || shift || meta {
+            return Vec::new();
+        }
+
+        self.keyboard_selection = false;
+        if windows.overlay_visible || windows.floating_visible {
+            return Vec::new();
+        }
+        let (x, y) = self.cursor;
+        vec![GestureAction::ShowOverlay { x, y, start_x: x }]
+    }
+
+    fn extends_selection(&self, key: Key) -> bool {
+        let navigation = matches!(
+            key,
+            Key::LeftArrow
+                | Key::RightArrow
+                | Key::UpArrow
+                | Key::DownArrow
+                | Key::Home
+                | Key::End
+                | Key::PageUp
+                | Key::PageDown
+        );
+        let select_all_modifier = if cfg!(target_os = "macos") {
+            self.modifiers.meta
+        } else {
+            self.modifiers.ctrl
+        };
+        (navigation && self.modifiers.shift)
+            || (key == Key::KeyA && select_all_modifier && !self.modifiers.shift)
+    }
+
+    fn press(&mut self, at: Instant, windows: &WindowState) -> Vec<GestureAction> {
+        // A click collapses whatever the keyboard selected.
+        self.keyboard_selection = false;
+        // While the panel is open, clicks only ever close it; they never
+        // start a selection.
+        if windows.floating_visible {
+            self.press_start = None;
+            self.moved = false;
+            self.end_click_streak();
+            if windows.inside_floating(self.cursor) {
+                return Vec::new();
+            }
+            return vec![GestureAction::HideFloating];
+        }
+
+        let window = Duration::from_millis(self.behavior.multi_click_ms);
+        let continues_streak = self.last_press.is_some_and(|(time, position)| {
+            at.saturating_duration_since(time) <= window
+                && distance(position, self.cursor) <= self.behavior.drag_threshold
+        });
+        self.click_count = if continues_streak {
+            self.click_count + 1
+        } else {
+            1
+        };
+        self.last_press = Some((at, self.cursor));
+
+        let mut actions = Vec::new();
+        if windows.inside_overlay(self.cursor) {
+            self.overlay_click = true;
+            self.end_click_streak();
+        } else if windows.overlay_visible && !continues_streak {
+            // The third click of a triple click keeps the bubble the double
+            // click showed.
+            actions.push(GestureAction::HideOverlay);
+        }
+        self.press_start = Some(self.cursor);
+        self.moved = false;
+        actions
+    }
+
+    fn release(&mut self, windows: &WindowState) -> Vec<GestureAction> {
+        let press_start = self.press_start.take();
+        let moved = std::mem::take(&mut self.moved);
+        if std::mem::take(&mut self.overlay_click) {
+            return vec![GestureAction::OpenPanel];
+        }
+
+        // Double and triple clicks select a word or a line in place.
+        let selected = moved || self.click_count >= 2;
+        match press_start {
+            Some((start_x, _))
+                if selected && !windows.overlay_visible && !windows.floating_visible =>
+            {
+                let (x, y) = self.cursor;
+                let start_x = if moved { start_x } else { x };
+                vec![GestureAction::ShowOverlay { x, y, start_x }]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn end_click_streak(&mut self) {
+        self.last_press = None;
+        self.click_count = 0;
+    }
+}
+
+fn distance((x1, y1): (f64, f64), (x2, y2): (f64, f64)) -> f64 {
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    (dx * dx + dy * dy).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRESS: EventType = EventType::ButtonPress(Button::Left);
+    const RELEASE: EventType = EventType::ButtonRelease(Button::Left);
+
+    fn move_to(x: f64, y: f64) -> EventType {
+        EventType::MouseMove { x, y }
+    }
+
+    /// A tracker on its own clock, so separate clicks in a test never pass
+    /// for a double click by accident.
+    struct Script {
+        tracker: GestureTracker,
+        now: Instant,
+    }
+
+    impl Default for Script {
+        fn default() -> Self {
+            Self {
+                tracker: GestureTracker::default(),
+                now: Instant::now(),
+            }
+        }
+    }
+
+    /// Feeds `events` a second apart, well outside the multi-click window.
+    fn run(script: &mut Script, windows: &WindowState, events: &[EventType]) -> Vec<GestureAction> {
+        run_every(script, Duration::from_secs(1), windows, events)
+    }
+
+    fn run_every(
+        script: &mut Script,
+        gap: Duration,
+        windows: &WindowState,
+        events: &[EventType],
+    ) -> Vec<GestureAction> {
+        events
+            .iter()
+            .flat_map(|event| {
+                script.now += gap;
+                script.tracker.handle(event, script.now, windows)
+            })
+            .collect()
+    }
+
+    fn overlay_at(x: f64, y: f64) -> WindowState {
+        WindowState {
+            overlay_visible: true,
+            overlay_bounds: Some((x, y, 32.0, 32.0)),
+            ..Default::default()
+        }
+    }
+
+    fn floating_at(x: f64, y: f64) -> WindowState {
+        WindowState {
+            floating_visible: true,
+            floating_bounds: Some((x, y, 120.0, 180.0)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn a_drag_shows_the_overlay_where_it_ended() {
+        let mut tracker = Script::default();
+        let actions = run(
+            &mut tracker,
+            &WindowState::default(),
+            &[move_to(100.0, 50.0), PRESS, move_to(180.0, 52.0), RELEASE],
+        );
+        assert_eq!(
+            actions,
+            vec![GestureAction::ShowOverlay {
+                x: 180.0,
+                y: 52.0,
+                start_x: 100.0
+            }]
+        );
+    }
+
+    #[test]
+    fn a_click_or_small_wobble_is_not_a_drag() {
+        let mut tracker = Script::default();
+        let windows = WindowState::default();
+        assert!(run(
+            &mut tracker,
+            &windows,
+            &[move_to(10.0, 10.0), PRESS, RELEASE]
+        )
+        .is_empty());
+        assert!(run(
+            &mut tracker,
+            &windows,
+            &[PRESS, move_to(13.0, 14.0), RELEASE]
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn moving_without_the_button_is_not_a_drag() {
+        let mut tracker = Script::default();
+        let windows = WindowState::default();
+        let actions = run(
+            &mut tracker,
+            &windows,
+            &[move_to(0.0, 0.0), move_to(300.0, 300.0), RELEASE],
+        );
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn a_new_drag_moves_the_overlay() {
+        let mut tracker = Script::default();
+        // Pressing away from the bubble hides it; the listener clears the
+        // visibility flag before the release arrives.
+        let actions = run(
+            &mut tracker,
+            &overlay_at(500.0, 500.0),
+            &[move_to(0.0, 0.0), PRESS],
+        );
+        assert_eq!(actions, vec![GestureAction::HideOverlay]);
+        let actions = run(
+            &mut tracker,
+            &WindowState::default(),
+            &[move_to(50.0, 0.0), RELEASE],
+        );
+        assert_eq!(
+            actions,
+            vec![GestureAction::ShowOverlay {
+                x: 50.0,
+                y: 0.0,
+                start_x: 0.0
+            }]
+        );
+    }
+
+    #[test]
+    fn a_drag_never_stacks_a_second_overlay() {
+        let mut tracker = Script::default();
+        run(&mut tracker, &WindowState::default(), &[PRESS]);
+        let actions = run(
+            &mut tracker,
+            &overlay_at(500.0, 500.0),
+            &[move_to(50.0, 0.0), RELEASE],
+        );
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn clicking_the_overlay_opens_the_panel() {
+        let mut tracker = Script::default();
+        let windows = overlay_at(100.0, 100.0);
+        let actions = run(
+            &mut tracker,
+            &windows,
+            &[move_to(110.0, 120.0), PRESS, move_to(140.0, 120.0), RELEASE],
+        );
+        assert_eq!(actions, vec![GestureAction::OpenPanel]);
+        // The click is over; a plain release afterwards does nothing.
+        assert!(run(&mut tracker, &windows, &[RELEASE]).is_empty());
+    }
+
+    #[test]
+    fn typing_cancels_an_overlay_click() {
+        let mut tracker = Script::default();
+        let windows = overlay_at(100.0, 100.0);
+        run(&mut tracker, &windows, &[move_to(110.0, 110.0), PRESS]);
+        let actions = run(
+            &mut tracker,
+            &windows,
+            &[EventType::KeyPress(rdev::Key::KeyA)],
+        );
+        assert_eq!(
+            actions,
+            vec![GestureAction::HideOverlay, GestureAction::HideFloating]
+        );
+        assert!(run(&mut tracker, &WindowState::default(), &[RELEASE]).is_empty());
+    }
+
+    #[test]
+    fn clicking_outside_the_panel_closes_it_without_starting_a_drag() {
+        let mut tracker = Script::default();
+        let windows = floating_at(100.0, 100.0);
+        let actions = run(
+            &mut tracker,
+            &windows,
+            &[move_to(10.0, 10.0), PRESS, move_to(90.0, 10.0)],
+        );
+        assert_eq!(actions, vec![GestureAction::HideFloating]);
+        assert!(run(&mut tracker, &WindowState::default(), &[RELEASE]).is_empty());
+    }
+
+    #[test]
+    fn clicks_inside_the_panel_are_left_to_it() {
+        let mut tracker = Script::default();
+        let windows = floating_at(100.0, 100.0);
+        let actions = run(
+            &mut tracker,
+            &windows,
+            &[move_to(150.0, 150.0), PRESS, move_to(200.0, 150.0), RELEASE],
+        );
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn other_buttons_are_ignored() {
+        let mut tracker = Script::default();
+        let actions = run(
+            &mut tracker,
+            &WindowState::default(),
+            &[
+                EventType::ButtonPress(Button::Right),
+                move_to(100.0, 100.0),
+                EventType::ButtonRelease(Button::Right),
+            ],
+        );
+        assert!(actions.is_empty());
+    }
+
+    const QUICKLY: Duration = Duration::from_millis(120);
+
+    #[test]
+    fn a_double_click_shows_the_overlay_beside_the_word() {
+        let mut tracker = Script::default();
+        let windows = WindowState::default();
+        let actions = run_every(
+            &mut tracker,
+            QUICKLY,
+            &windows,
+            &[
+                move_to(300.0, 200.0),
+                PRESS,
+                RELEASE,
+                move_to(302.0, 201.0),
+                PRESS,
+                RELEASE,
+            ],
+        );
+        assert_eq!(
+            actions,
+            vec![GestureAction::ShowOverlay {
+                x: 302.0,
+                y: 201.0,
+                start_x: 302.0
+            }]
+        );
+    }
+
+    #[test]
+    fn a_triple_click_keeps_the_double_click_overlay() {
+        let mut tracker = Script::default();
+        run_every(
+            &mut tracker,
+            QUICKLY,
+            &WindowState::default(),
+            &[move_to(300.0, 200.0), PRESS, RELEASE, PRESS, RELEASE],
+        );
+        // The listener has shown the bubble by the third click.
+        let actions = run_every(
+            &mut tracker,
+            QUICKLY,
+            &overlay_at(310.0, 210.0),
+            &[PRESS, RELEASE],
+        );
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn slow_or_distant_clicks_are_separate() {
+        let windows = WindowState::default();
+        let mut tracker = Script::default();
+        tracker.tracker.set_behavior(BehaviorSettings {
+            multi_click_ms: 300,
+            ..Default::default()
+        });
+        let slow = run_every(
+            &mut tracker,
+            Duration::from_millis(200),
+            &windows,
+            &[move_to(300.0, 200.0), PRESS, RELEASE, PRESS, RELEASE],
+        );
+        assert!(slow.is_empty());
+
+        let mut tracker = Script::default();
+        let distant = run_every(
+            &mut tracker,
+            QUICKLY,
+            &windows,
+            &[
+                move_to(300.0, 200.0),
+                PRESS,
+                RELEASE,
+                move_to(340.0, 200.0),
+                PRESS,
+                RELEASE,
+            ],
+        );
+        assert!(distant.is_empty());
+    }
+
+    #[test]
+    fn out_of_range_behavior_is_rejected_or_clamped() {
+        let settings: BehaviorSettings =
+            serde_json::from_str(r#"{"multiClickMs":60000,"dragThreshold":0.5}"#).unwrap();
+        assert_eq!(
+            settings.validate().unwrap_err(),
+            vec![
+                "Double-click window must be between 100 and 2000",
+                "Drag threshold must be between 1 and 50",
+            ]
+        );
+        let clamped = settings.clamped();
+        assert_eq!(clamped.multi_click_ms, MULTI_CLICK_MS_RANGE.1);
+        assert_eq!(clamped.drag_threshold, DRAG_THRESHOLD_RANGE.0);
+        assert_eq!(
+            clamped.overlay_size,
+            BehaviorSettings::default().overlay_size
+        );
+
+        let settings: BehaviorSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings, BehaviorSettings::default());
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn a_larger_threshold_applies_immediately() {
+        let mut tracker = Script::default();
+        tracker.tracker.set_behavior(BehaviorSettings {
+            drag_threshold: 20.0,
+            ..Default::default()
+        });
+        let windows = WindowState::default();
+        let wobble = run(
+            &mut tracker,
+            &windows,
+            &[move_to(100.0, 100.0), PRESS, move_to(115.0, 100.0), RELEASE],
+        );
+        assert!(wobble.is_empty());
+    }
+
+    #[test]
+    fn clicks_hit_the_bubble_as_it_was_placed() {
+        // A 32px bubble is 48 physical pixels at 150% scaling.
+        let windows = WindowState {
+            overlay_visible: true,
+            overlay_bounds: Some((100.0, 100.0, 48.0, 48.0)),
+            ..Default::default()
+        };
+        let actions = run(
+            &mut Script::default(),
+            &windows,
+            &[move_to(140.0, 140.0), PRESS, RELEASE],
+        );
+        assert_eq!(actions, vec![GestureAction::OpenPanel]);
+    }
+
+    fn press(key: Key) -> EventType {
+        EventType::KeyPress(key)
+    }
+
+    fn release(key: Key) -> EventType {
+        EventType::KeyRelease(key)
+    }
+
+    fn shown(actions: Vec<GestureAction>) -> Vec<GestureAction> {
+        actions
+            .into_iter()
+            .filter(|action| matches!(action, GestureAction::ShowOverlay { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn shift_selection_shows_the_overlay_when_shift_is_released() {
+        let mut tracker = Script::default();
+        let windows = WindowState::default();
+        let actions = run(
+            &mut tracker,
+            &windows,
+            &[
+                move_to(600.0, 300.0),
+                press(Key::ShiftLeft),
+                press(Key::RightArrow),
+                release(Key::RightArrow),
+                press(Key::End),
+                release(Key::End),
+            ],
+        );
+        assert!(shown(actions).is_empty());
+        assert_eq!(
+            run(&mut tracker, &windows, &[release(Key::ShiftLeft)]),
+            vec![GestureAction::ShowOverlay {
+                x: 600.0,
+                y: 300.0,
+                start_x: 600.0
+            }]
+        );
+    }
+
+    #[test]
+    fn select_all_shows_the_overlay_when_the_modifier_is_released() {
+        let modifier = if cfg!(target_os = "macos") {
+            Key::MetaLeft
+        } else {
+            Key::ControlLeft
+        };
+        let mut tracker = Script::default();
+        let actions = run(
+            &mut tracker,
+            &WindowState::default(),
+            &[
+                press(modifier),
+                press(Key::KeyA),
+                release(Key::KeyA),
+                release(modifier),
+            ],
+        );
+        assert_eq!(shown(actions).len(), 1);
+    }
+
+    #[test]
+    fn typing_or_plain_navigation_is_not_a_selection() {
+        let mut tracker = Script::default();
+        let windows = WindowState::default();
+        // Shift+Right selects, then Shift+X types over the selection.
+        let typed_over = run(
+            &mut tracker,
+            &windows,
+            &[
+                press(Key::ShiftLeft),
+                press(Key::RightArrow),
+                press(Key::KeyX),
+                release(Key::ShiftLeft),
+            ],
+        );
+        assert!(shown(typed_over).is_empty());
+
+        let moved = run(
+            &mut tracker,
+            &windows,
+            &[
+                press(Key::RightArrow),
+                press(Key::ShiftLeft),
+                release(Key::ShiftLeft),
+            ],
+        );
+        assert!(shown(moved).is_empty());
+    }
+
+    #[test]
+    fn a_simulated_shift_selection_does_not_show_the_overlay() {
+        // What the listener passes on: key events are dropped while our own
+        // input is in flight.
+        fn listen(script: &mut Script, events: &[EventType]) -> Vec<GestureAction> {
+            let heard: Vec<EventType> = events
+                .iter()
+                .filter(|_| !crate::simulate::is_simulating())
+                .copied()
+                .collect();
+            run(script, &WindowState::default(), &heard)
+        }
+
+        let mut tracker = Script::default();
+        let simulating = crate::simulate::Simulating::start();
+        let mut actions = listen(
+            &mut tracker,
+            &[
+                press(Key::ShiftLeft),
+                press(Key::LeftArrow),
+                release(Key::LeftArrow),
+                press(Key::LeftArrow),
+                release(Key::LeftArrow),
+            ],
+        );
+        drop(simulating);
+        // The last event can be delivered after the guard is gone.
+        actions.extend(listen(&mut tracker, &[release(Key::ShiftLeft)]));
+        assert!(shown(actions).is_empty());
+    }
+
+    #[test]
+    fn a_click_collapses_a_keyboard_selection() {
+        let mut tracker = Script::default();
+        let actions = run(
+            &mut tracker,
+            &WindowState::default(),
+            &[
+                press(Key::ShiftLeft),
+                press(Key::LeftArrow),
+                PRESS,
+                RELEASE,
+                release(Key::ShiftLeft),
+            ],
+        );
+        assert!(shown(actions).is_empty());
+    }
+}
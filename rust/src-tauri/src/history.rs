@@ -0,0 +1,224 @@
+use crate::{focus, output, pipeline::PresetRunner, simulate};
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, Manager};
+use unicode_segmentation::UnicodeSegmentation;
+
+const HISTORY_LIMIT: usize = 20;
+// Undo selects the replacement with Shift+Left, one event pair per caret step;
+// beyond this it would take too long to be useful.
+const MAX_UNDO_LENGTH: usize = 5_000;
+const SELECT_EVENT_DELAY: Duration = Duration::from_millis(1);
+// Lets the user let go of the undo hotkey's modifiers, which would otherwise
+// combine with the synthetic Shift+Left.
+const MODIFIER_RELEASE_DELAY: Duration = Duration::from_millis(250);
+
+pub(crate) const DEFAULT_UNDO_HOTKEY: &str = "CommandOrControl+Alt+Z";
+
+/// One inline replacement GoBuddy made in another app.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReplacementEntry {
+    pub(crate) id: u64,
+    pub(crate) original: String,
+    pub(crate) replacement: String,
+    pub(crate) target_app: Option<String>,
+    /// How the replacement was written, and so how undo writes the original
+    /// back.
+    pub(crate) mode: output::OutputMode,
+    /// Milliseconds since the Unix epoch.
+    pub(crate) created_at: u64,
+}
+
+/// The most recent inline replacements, newest last. Only the newest can be
+/// undone, and only until the user presses a key, clicks or switches focus:
+/// undo selects the replacement relative to the caret, which any of those
+/// may have moved.
+#[derive(Default)]
+pub(crate) struct ReplacementHistory {
+    next_id: Mutex<u64>,
+    entries: Mutex<VecDeque<ReplacementEntry>>,
+    /// Id of the entry undo may still revert.
+    undoable: Mutex<Option<u64>>,
+}
+
+impl ReplacementHistory {
+    pub(crate) fn record(
+        &self,
+        original: String,
+        replacement: String,
+        target_app: Option<String>,
+        mode: output::OutputMode,
+    ) {
+        let mut next_id = self.next_id.lock().unwrap();
+        *next_id += 1;
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == HISTORY_LIMIT {
+            entries.pop_front();
+        }
+        entries.push_back(ReplacementEntry {
+            id: *next_id,
+            original,
+            replacement,
+            target_app,
+            mode,
+            created_at,
+        });
+        *self.undoable.lock().unwrap() = Some(*next_id);
+    }
+
+    /// Called when the caret may have moved away from the last replacement.
+    pub(crate) fn forget_undo(&self) {
+        self.undoable.lock().unwrap().take();
+    }
+
+    /// Entries newest first.
+    pub(crate) fn entries(&self) -> Vec<ReplacementEntry> {
+        self.entries.lock().unwrap().iter().rev().cloned().collect()
+    }
+
+    /// The newest entry, if the caret can still be trusted to sit right
+    /// after it.
+    fn undoable(&self) -> Option<ReplacementEntry> {
+        let id = (*self.undoable.lock().unwrap())?;
+        self.entries
+            .lock()
+            .unwrap()
+            .back()
+            .filter(|entry| entry.id == id)
+            .cloned()
+    }
+
+    fn remove(&self, id: u64) {
+        self.entries.lock().unwrap().retain(|entry| entry.id != id);
+    }
+}
+
+/// Puts back the text the most recent replacement overwrote, as long as
+/// nothing has happened since that could have moved the caret. Older entries
+/// are never undone: the caret doesn't sit next to them.
+pub(crate) async fn undo_last_replacement(app: &AppHandle) -> Result<ReplacementEntry, String> {
+    let runner = app.state::<PresetRunner>();
+    let _guard = runner.try_acquire().ok_or("A preset is still running")?;
+    let history = app.state::<ReplacementHistory>();
+    let entry = history
+        .undoable()
+        .ok_or("There is no GoBuddy replacement to undo")?;
+    // Whatever happens below, the caret is no longer where it was.
+    history.forget_undo();
+
+    let settings = app
+        .state::<crate::PresetStateStore>()
+        .load_state()?
+        .and_then(|state| state.settings)
+        .unwrap_or_default();
+    let undo_entry = entry.clone();
+    tauri::async_runtime::spawn_blocking(move || restore_original(&undo_entry, &settings.output))
+        .await
+        .map_err(|err| err.to_string())??;
+
+    history.remove(entry.id);
+    Ok(entry)
+}
+
+fn restore_original(
+    entry: &ReplacementEntry,
+    settings: &output::OutputSettings,
+) -> Result<(), String> {
+    thread::sleep(MODIFIER_RELEASE_DELAY);
+    if let (Some(expected), Some(focused)) = (&entry.target_app, focus::focused_app()) {
+        if !expected.eq_ignore_ascii_case(&focused) {
+            return Err(format!(
+                "The last replacement was made in {}, but {} is focused",
+                expected, focused
+            ));
+        }
+    }
+
+    let length = caret_length(&entry.replacement);
+    if length > MAX_UNDO_LENGTH {
+        return Err("The last replacement is too long to undo".into());
+    }
+    // One guard across both steps, so the Shift+Left run and the paste
+    // read as a single piece of synthetic input.
+    let _simulating = simulate::Simulating::start();
+    simulate::select_backwards(length, SELECT_EVENT_DELAY)?;
+    output::replace_selection(&entry.original, entry.mode, settings)
+}
+
+/// Number of caret steps across `text`. Editors step over a whole grapheme
+/// cluster at a time: a letter with its combining marks, a ZWJ emoji
+/// sequence, or CRLF.
+fn caret_length(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_keeps_the_most_recent_entries() {
+        let history = ReplacementHistory::default();
+        for index in 0..HISTORY_LIMIT + 2 {
+            history.record(
+                format!("before {}", index),
+                format!("after {}", index),
+                None,
+                output::OutputMode::Paste,
+            );
+        }
+
+        let entries = history.entries();
+        assert_eq!(entries.len(), HISTORY_LIMIT);
+        assert_eq!(entries[0].original, format!("before {}", HISTORY_LIMIT + 1));
+        assert_eq!(entries.last().unwrap().original, "before 2");
+    }
+
+    #[test]
+    fn only_the_newest_entry_is_undoable_until_forgotten() {
+        let history = ReplacementHistory::default();
+        assert!(history.undoable().is_none());
+        history.record("a".into(), "A".into(), None, output::OutputMode::Paste);
+        history.record("b".into(), "B".into(), None, output::OutputMode::Type);
+
+        let entry = history.undoable().unwrap();
+        assert_eq!(entry.original, "b");
+        assert_eq!(entry.mode, output::OutputMode::Type);
+
+        history.forget_undo();
+        assert!(history.undoable().is_none());
+        // Undoing "b" mustn't make "a" the target.
+        history.remove(entry.id);
+        assert!(history.undoable().is_none());
+        assert_eq!(history.entries().len(), 1);
+    }
+
+    #[test]
+    fn caret_length_counts_crlf_once() {
+        assert_eq!(caret_length("héllo\r\nwörld\n"), 12);
+    }
+
+    #[test]
+    fn caret_length_counts_combining_marks_with_their_letter() {
+        // "e" + COMBINING ACUTE ACCENT, "n" + COMBINING TILDE.
+        assert_eq!(caret_length("cafe\u{301} man\u{303}ana"), 11);
+    }
+
+    #[test]
+    fn caret_length_counts_a_zwj_emoji_once() {
+        // Man, woman, girl and boy joined into one family emoji.
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}\u{200D}\u{1F466}";
+        assert_eq!(caret_length(&format!("hi {}!", family)), 5);
+    }
+}
@@ -0,0 +1,1080 @@
+use crate::{
+    chords::{self, ChordMatcher, ChordProgress, ChordStep},
+    history, pipeline, PersistedState, Preset, PresetView, MAIN_WINDOW_LABEL,
+};
+use serde::Serialize;
+use std::{sync::Mutex, thread, time::Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+pub(crate) const HOTKEY_DIAGNOSTICS_EVENT: &str = "gobuddy://hotkey-diagnostics";
+
+/// What a global hotkey does when pressed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum HotkeyAction {
+    /// Runs the view's active preset on the current selection.
+    View { view: PresetView },
+    /// Runs one preset directly, skipping the floating panel.
+    #[serde(rename_all = "camelCase")]
+    Preset {
+        view: PresetView,
+        preset_id: String,
+        preset_name: String,
+    },
+    /// Runs one preset from a key sequence.
+    #[serde(rename_all = "camelCase")]
+    Chord {
+        view: PresetView,
+        preset_id: String,
+        preset_name: String,
+    },
+    /// Undoes the last inline replacement.
+    Undo,
+}
+
+impl HotkeyAction {
+    fn describe(&self) -> String {
+        match self {
+            HotkeyAction::View { view } => format!("the {} hotkey", view.label()),
+            HotkeyAction::Preset { preset_name, .. } => format!("preset \"{}\"", preset_name),
+            HotkeyAction::Chord { preset_name, .. } => {
+                format!("the key sequence of preset \"{}\"", preset_name)
+            }
+            HotkeyAction::Undo => "the undo hotkey".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct HotkeyBinding {
+    pub(crate) accelerator: String,
+    pub(crate) shortcut: Shortcut,
+    pub(crate) action: HotkeyAction,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ChordBinding {
+    /// Normalized display form, such as "Ctrl+Space, T".
+    pub(crate) chord: String,
+    pub(crate) steps: Vec<ChordStep>,
+    /// The global shortcut each step is registered as.
+    pub(crate) shortcuts: Vec<Shortcut>,
+    pub(crate) action: HotkeyAction,
+}
+
+/// A hotkey that could not be bound. Serialized for the settings UI.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub(crate) enum HotkeyError {
+    #[serde(rename_all = "camelCase")]
+    InvalidAccelerator {
+        action: HotkeyAction,
+        accelerator: String,
+        message: String,
+    },
+    /// Two hotkeys share an accelerator, one key sequence starts with
+    /// another, or a step of a sequence is also a hotkey; `first` is the one
+    /// listed earlier.
+    #[serde(rename_all = "camelCase")]
+    Conflict {
+        accelerator: String,
+        first: HotkeyAction,
+        second: HotkeyAction,
+        message: String,
+    },
+}
+
+impl HotkeyError {
+    pub(crate) fn message(&self) -> &str {
+        match self {
+            HotkeyError::InvalidAccelerator { message, .. }
+            | HotkeyError::Conflict { message, .. } => message,
+        }
+    }
+}
+
+/// Where one configured hotkey stands.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub(crate) enum RegistrationState {
+    Registered,
+    /// Released while the UI records a new hotkey.
+    Paused,
+    /// Refused by the OS, usually because another app already holds it.
+    Failed {
+        message: String,
+    },
+    /// Never registered: it doesn't parse or duplicates an earlier hotkey.
+    Invalid {
+        error: HotkeyError,
+    },
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HotkeyStatus {
+    pub(crate) action: HotkeyAction,
+    pub(crate) accelerator: String,
+    #[serde(flatten)]
+    pub(crate) state: RegistrationState,
+}
+
+/// Payload of `HOTKEY_DIAGNOSTICS_EVENT`: every hotkey that isn't working.
+/// Sent after each registration pass, so an empty list clears old warnings.
+#[derive(Clone, Debug, Serialize)]
+struct HotkeyDiagnostics {
+    failures: Vec<HotkeyStatus>,
+}
+
+/// The hotkeys from the persisted config, split into bindable ones and the
+/// ones rejected up front.
+#[derive(Debug, Default)]
+pub(crate) struct HotkeyPlan {
+    pub(crate) bindings: Vec<HotkeyBinding>,
+    pub(crate) chords: Vec<ChordBinding>,
+    pub(crate) errors: Vec<HotkeyError>,
+}
+
+/// Global shortcuts the backend has registered, so later saves can replace
+/// exactly those and leave any others alone, plus the key sequences they
+/// lead into.
+#[derive(Default)]
+pub(crate) struct HotkeyRegistry {
+    state: Mutex<RegistryState>,
+    /// Apart from `state` so pressing a step never waits on a registration
+    /// pass.
+    sequence: Mutex<SequenceState>,
+    /// Shortcuts registered for the steps that may come next. Held while
+    /// registering, so the threads syncing them take turns.
+    followers: Mutex<Vec<Shortcut>>,
+    /// Key presses of the registered hotkeys, for the input listener.
+    hotkey_steps: Mutex<Vec<ChordStep>>,
+}
+
+#[derive(Default)]
+struct RegistryState {
+    bindings: Vec<HotkeyBinding>,
+    chords: Vec<ChordBinding>,
+    /// Registration outcome per binding, in the same order.
+    outcomes: Vec<Result<(), String>>,
+    /// Registration outcome of each chord's leader, in the order of `chords`.
+    chord_outcomes: Vec<Result<(), String>>,
+    errors: Vec<HotkeyError>,
+    paused: bool,
+}
+
+/// The key sequence under way.
+#[derive(Default)]
+struct SequenceState {
+    matcher: ChordMatcher<HotkeyAction>,
+    /// Every step with its shortcut, so the next steps can be registered.
+    shortcuts: Vec<(ChordStep, Shortcut)>,
+    /// First steps, which stay registered.
+    leaders: Vec<ChordStep>,
+}
+
+impl SequenceState {
+    /// Follows a key press, or returns `None` when it neither starts nor
+    /// continues a sequence.
+    fn press(&mut self, step: ChordStep, now: Instant) -> Option<ChordProgress<HotkeyAction>> {
+        if !self.matcher.is_pending() && !self.leaders.contains(&step) {
+            return None;
+        }
+        Some(self.matcher.press(step, now))
+    }
+
+    /// Shortcuts to register for the steps that may come next, leaving out
+    /// leaders.
+    fn followers(&self) -> Vec<Shortcut> {
+        self.matcher
+            .next_steps()
+            .iter()
+            .filter(|step| !self.leaders.contains(step))
+            .filter_map(|step| {
+                self.shortcuts
+                    .iter()
+                    .find(|(known, _)| known == step)
+                    .map(|(_, shortcut)| *shortcut)
+            })
+            .collect()
+    }
+}
+
+impl RegistryState {
+    fn statuses(&self) -> Vec<HotkeyStatus> {
+        let registered = self
+            .bindings
+            .iter()
+            .zip(&self.outcomes)
+            .map(|(binding, outcome)| HotkeyStatus {
+                action: binding.action.clone(),
+                accelerator: binding.accelerator.clone(),
+                state: match outcome {
+                    _ if self.paused => RegistrationState::Paused,
+                    Ok(()) => RegistrationState::Registered,
+                    Err(message) => RegistrationState::Failed {
+                        message: message.clone(),
+                    },
+                },
+            });
+        // A sequence stands or falls with its leader; the later steps are
+        // only registered while it is under way.
+        let sequences = self
+            .chords
+            .iter()
+            .zip(&self.chord_outcomes)
+            .map(|(chord, outcome)| HotkeyStatus {
+                action: chord.action.clone(),
+                accelerator: chord.chord.clone(),
+                state: match outcome {
+                    _ if self.paused => RegistrationState::Paused,
+                    Ok(()) => RegistrationState::Registered,
+                    Err(message) => RegistrationState::Failed {
+                        message: message.clone(),
+                    },
+                },
+            });
+        let rejected = self.errors.iter().map(|error| {
+            let (action, accelerator) = match error {
+                HotkeyError::InvalidAccelerator {
+                    action,
+                    accelerator,
+                    ..
+                } => (action, accelerator),
+                HotkeyError::Conflict {
+                    second,
+                    accelerator,
+                    ..
+                } => (second, accelerator),
+            };
+            HotkeyStatus {
+                action: action.clone(),
+                accelerator: accelerator.clone(),
+                state: RegistrationState::Invalid {
+                    error: error.clone(),
+                },
+            }
+        });
+        registered.chain(sequences).chain(rejected).collect()
+    }
+}
+
+/// Turns the UI's display form ("Ctrl + Shift + S") into an accelerator the
+/// global-shortcut plugin parses ("Ctrl+Shift+S").
+pub(crate) fn normalize_accelerator(display: &str) -> String {
+    display
+        .split('+')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| match part.to_ascii_lowercase().as_str() {
+            "meta" | "win" | "windows" => "Super",
+            _ => part,
+        })
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// Every hotkey configured in `state`, per view and per preset. Unset hotkeys
+/// are skipped; parse failures and duplicates are collected alongside.
+pub(crate) fn plan(state: &PersistedState) -> HotkeyPlan {
+    let hotkeys = state.hotkeys.clone().unwrap_or_default();
+    let configured = [
+        (
+            &hotkeys.screenshot,
+            HotkeyAction::View {
+                view: PresetView::Screenshot,
+            },
+        ),
+        (
+            &hotkeys.input_field,
+            HotkeyAction::View {
+                view: PresetView::InputField,
+            },
+        ),
+        (
+            &hotkeys.selection,
+            HotkeyAction::View {
+                view: PresetView::Selection,
+            },
+        ),
+        (&hotkeys.undo, HotkeyAction::Undo),
+    ]
+    .into_iter()
+    .map(|(display, action)| (display.clone(), action));
+    let preset_hotkeys = all_presets(state).filter_map(|(view, preset)| {
+        let accelerator = preset.accelerator.clone()?;
+        Some((
+            accelerator,
+            HotkeyAction::Preset {
+                view,
+                preset_id: preset.id.clone(),
+                preset_name: preset.name.clone(),
+            },
+        ))
+    });
+
+    let mut bindings: Vec<HotkeyBinding> = Vec::new();
+    let mut errors = Vec::new();
+    for (display, action) in configured.chain(preset_hotkeys) {
+        let accelerator = normalize_accelerator(&display);
+        if accelerator.is_empty() {
+            continue;
+        }
+        let shortcut = match accelerator.parse::<Shortcut>() {
+            Ok(shortcut) => shortcut,
+            Err(err) => {
+                errors.push(HotkeyError::InvalidAccelerator {
+                    action,
+                    accelerator,
+                    message: err.to_string(),
+                });
+                continue;
+            }
+        };
+
+        match bindings
+            .iter()
+            .find(|existing| existing.shortcut == shortcut)
+        {
+            Some(existing) => errors.push(HotkeyError::Conflict {
+                message: format!(
+                    "{} and {} both use {}",
+                    existing.action.describe(),
+                    action.describe(),
+                    accelerator
+                ),
+                accelerator,
+                first: existing.action.clone(),
+                second: action,
+            }),
+            None => bindings.push(HotkeyBinding {
+                accelerator,
+                shortcut,
+                action,
+            }),
+        }
+    }
+
+    let chords = plan_chords(state, &bindings, &mut errors);
+    HotkeyPlan {
+        bindings,
+        chords,
+        errors,
+    }
+}
+
+/// Key sequences of every preset. Besides parse failures, a sequence that
+/// equals or starts another one is rejected, since the shorter one would
+/// always fire first, and so is one with a step that is already a hotkey,
+/// since the hotkey would take that step.
+fn plan_chords(
+    state: &PersistedState,
+    bindings: &[HotkeyBinding],
+    errors: &mut Vec<HotkeyError>,
+) -> Vec<ChordBinding> {
+    let mut chords: Vec<ChordBinding> = Vec::new();
+    for (view, preset) in all_presets(state) {
+        let Some(display) = preset
+            .chord
+            .as_deref()
+            .filter(|chord| !chord.trim().is_empty())
+        else {
+            continue;
+        };
+        let chord = display
+            .split(',')
+            .map(normalize_accelerator)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let action = HotkeyAction::Chord {
+            view,
+            preset_id: preset.id.clone(),
+            preset_name: preset.name.clone(),
+        };
+        let parsed = chords::parse_chord(&chord).and_then(|steps| {
+            let shortcuts = chord
+                .split(", ")
+                .map(|step| {
+                    step.parse::<Shortcut>()
+                        .map_err(|err| format!("\"{}\": {}", step, err))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((steps, shortcuts))
+        });
+        let (steps, shortcuts) = match parsed {
+            Ok(parsed) => parsed,
+            Err(message) => {
+                errors.push(HotkeyError::InvalidAccelerator {
+                    action,
+                    accelerator: chord,
+                    message,
+                });
+                continue;
+            }
+        };
+
+        let taken = chord
+            .split(", ")
+            .zip(&shortcuts)
+            .find_map(|(step, shortcut)| {
+                bindings
+                    .iter()
+                    .find(|binding| binding.shortcut == *shortcut)
+                    .map(|binding| (step, binding))
+            });
+        if let Some((step, binding)) = taken {
+            errors.push(HotkeyError::Conflict {
+                message: format!(
+                    "{} and {} both use {}",
+                    binding.action.describe(),
+                    action.describe(),
+                    step
+                ),
+                accelerator: chord,
+                first: binding.action.clone(),
+                second: action,
+            });
+            continue;
+        }
+
+        let overlapping = chords.iter().find(|existing| {
+            existing.steps.starts_with(&steps) || steps.starts_with(&existing.steps)
+        });
+        match overlapping {
+            Some(existing) => errors.push(HotkeyError::Conflict {
+                message: if existing.steps == steps {
+                    format!(
+                        "{} and {} both use {}",
+                        existing.action.describe(),
+                        action.describe(),
+                        chord
+                    )
+                } else {
+                    format!(
+                        "{} ({}) and {} ({}) overlap, so the shorter one always runs first",
+                        existing.action.describe(),
+                        existing.chord,
+                        action.describe(),
+                        chord
+                    )
+                },
+                accelerator: chord,
+                first: existing.action.clone(),
+                second: action,
+            }),
+            None => chords.push(ChordBinding {
+                chord,
+                steps,
+                shortcuts,
+                action,
+            }),
+        }
+    }
+    chords
+}
+
+fn all_presets(state: &PersistedState) -> impl Iterator<Item = (PresetView, &Preset)> {
+    [
+        PresetView::Screenshot,
+        PresetView::InputField,
+        PresetView::Selection,
+    ]
+    .into_iter()
+    .flat_map(move |view| {
+        state
+            .presets
+            .for_view(view)
+            .iter()
+            .map(move |preset| (view, preset))
+    })
+}
+
+/// Replaces the backend's registered shortcuts with the plan's bindings and
+/// reports anything that failed to the main window. Shortcuts the OS refuses
+/// are skipped so the rest still work.
+pub(crate) fn apply(app: &AppHandle, plan: HotkeyPlan) {
+    let registry = app.state::<HotkeyRegistry>();
+    let mut state = registry.state.lock().unwrap();
+    let unchanged = state.bindings == plan.bindings
+        && state.chords == plan.chords
+        && state.errors == plan.errors
+        && state.outcomes.iter().all(Result::is_ok)
+        && state.chord_outcomes.iter().all(Result::is_ok);
+    if unchanged {
+        return;
+    }
+
+    unregister_all(app, &state);
+    state.bindings = plan.bindings;
+    state.chords = plan.chords;
+    state.errors = plan.errors;
+    state.outcomes = vec![Ok(()); state.bindings.len()];
+    state.chord_outcomes = vec![Ok(()); state.chords.len()];
+    if !state.paused {
+        register_all(app, &mut state);
+    }
+    arm_chords(&registry, &state);
+    claim_hotkey_steps(&registry, &state);
+    emit_diagnostics(app, &state);
+}
+
+/// Reads the hotkeys from `state` and registers the valid ones.
+pub(crate) fn sync(app: &AppHandle, state: &PersistedState) {
+    apply(app, plan(state));
+}
+
+/// Current status of every configured hotkey, including rejected ones.
+pub(crate) fn statuses(app: &AppHandle) -> Vec<HotkeyStatus> {
+    app.state::<HotkeyRegistry>()
+        .state
+        .lock()
+        .unwrap()
+        .statuses()
+}
+
+/// Temporarily releases every hotkey, e.g. while the UI records a new one and
+/// needs the keystrokes to reach the webview.
+pub(crate) fn set_paused(app: &AppHandle, paused: bool) {
+    let registry = app.state::<HotkeyRegistry>();
+    let mut state = registry.state.lock().unwrap();
+    if state.paused == paused {
+        return;
+    }
+    state.paused = paused;
+    arm_chords(&registry, &state);
+    if paused {
+        unregister_all(app, &state);
+    } else {
+        register_all(app, &mut state);
+        emit_diagnostics(app, &state);
+    }
+    claim_hotkey_steps(&registry, &state);
+}
+
+/// Whether `step` is the key press of a registered hotkey. The OS hands
+/// those to GoBuddy, so they never reach the focused app.
+pub(crate) fn is_hotkey(app: &AppHandle, step: ChordStep) -> bool {
+    app.try_state::<HotkeyRegistry>()
+        .is_some_and(|registry| registry.hotkey_steps.lock().unwrap().contains(&step))
+}
+
+fn register_all(app: &AppHandle, state: &mut RegistryState) {
+    let RegistryState {
+        bindings,
+        outcomes,
+        chords,
+        chord_outcomes,
+        ..
+    } = state;
+    for (binding, outcome) in bindings.iter().zip(outcomes.iter_mut()) {
+        let action = binding.action.clone();
+        *outcome = app
+            .global_shortcut()
+            .on_shortcut(binding.shortcut, move |app, _, event| {
+                if event.state == ShortcutState::Pressed {
+                    trigger(app, &action);
+                }
+            })
+            .map_err(|err| err.to_string());
+    }
+
+    // Sequences often share a leader, which is registered once for all.
+    let mut leaders: Vec<(Shortcut, Result<(), String>)> = Vec::new();
+    for (chord, outcome) in chords.iter().zip(chord_outcomes.iter_mut()) {
+        let leader = chord.shortcuts[0];
+        if let Some((_, registered)) = leaders.iter().find(|(shortcut, _)| *shortcut == leader) {
+            *outcome = registered.clone();
+            continue;
+        }
+        *outcome = register_step(app, leader);
+        leaders.push((leader, outcome.clone()));
+    }
+}
+
+/// Registers a step without a handler: the shortcut only keeps the key from
+/// the focused app, while `key_pressed` follows the sequence.
+fn register_step(app: &AppHandle, shortcut: Shortcut) -> Result<(), String> {
+    app.global_shortcut()
+        .register(shortcut)
+        .map_err(|err| err.to_string())
+}
+
+/// Records the key presses of the hotkeys that registered, or none while
+/// paused. Accelerators naming keys by code, such as "KeyZ", aren't
+/// recognized and count as typing.
+fn claim_hotkey_steps(registry: &HotkeyRegistry, state: &RegistryState) {
+    let steps = state
+        .bindings
+        .iter()
+        .zip(&state.outcomes)
+        .filter(|(_, outcome)| !state.paused && outcome.is_ok())
+        .filter_map(|(binding, _)| chords::parse_step(&binding.accelerator).ok())
+        .collect();
+    *registry.hotkey_steps.lock().unwrap() = steps;
+}
+
+/// Hands the key sequences to the matcher, or none while paused.
+fn arm_chords(registry: &HotkeyRegistry, state: &RegistryState) {
+    let chords: &[ChordBinding] = if state.paused { &[] } else { &state.chords };
+    let mut sequence = registry.sequence.lock().unwrap();
+    sequence.matcher.set_chords(
+        chords
+            .iter()
+            .map(|chord| (chord.steps.clone(), chord.action.clone()))
+            .collect(),
+    );
+    sequence.shortcuts = chords
+        .iter()
+        .flat_map(|chord| chord.steps.iter().copied().zip(chord.shortcuts.clone()))
+        .collect();
+    sequence.leaders = chords.iter().map(|chord| chord.steps[0]).collect();
+}
+
+/// Feeds a key press from the input listener to the matcher, and returns
+/// whether it started, continued or completed a sequence. Any other key
+/// breaks off the sequence under way. While a sequence is under way the
+/// steps that may come next are registered too, so they don't reach the
+/// focused app either; a step pressed before that happens still counts.
+pub(crate) fn key_pressed(app: &AppHandle, step: ChordStep) -> bool {
+    let Some(registry) = app.try_state::<HotkeyRegistry>() else {
+        return false;
+    };
+    let Some(progress) = registry
+        .sequence
+        .lock()
+        .unwrap()
+        .press(step, Instant::now())
+    else {
+        return false;
+    };
+
+    let pending = matches!(progress, ChordProgress::Pending(_));
+    // Registering goes through the main thread, which the listener must not
+    // wait on.
+    let background = app.clone();
+    thread::spawn(move || {
+        sync_followers(&background);
+        if pending {
+            thread::sleep(chords::CHORD_TIMEOUT);
+            let registry = background.state::<HotkeyRegistry>();
+            let expired = registry
+                .sequence
+                .lock()
+                .unwrap()
+                .matcher
+                .expire(Instant::now());
+            if expired {
+                sync_followers(&background);
+            }
+        }
+    });
+
+    match progress {
+        ChordProgress::Completed(action) => {
+            trigger(app, &action);
+            true
+        }
+        ChordProgress::Pending(_) => true,
+        ChordProgress::Idle => false,
+    }
+}
+
+/// Registers the followers of the sequence as it stands now, so threads
+/// that run out of order still leave the latest ones.
+fn sync_followers(app: &AppHandle) {
+    let registry = app.state::<HotkeyRegistry>();
+    let mut followers = registry.followers.lock().unwrap();
+    let next = registry.sequence.lock().unwrap().followers();
+    hold_followers(app, &mut followers, next);
+}
+
+/// Leaves exactly the shortcuts of `next` registered as followers.
+fn hold_followers(app: &AppHandle, followers: &mut Vec<Shortcut>, next: Vec<Shortcut>) {
+    followers.retain(|shortcut| {
+        let keep = next.contains(shortcut);
+        if !keep {
+            let _ = app.global_shortcut().unregister(*shortcut);
+        }
+        keep
+    });
+    for shortcut in next {
+        if followers.contains(&shortcut) {
+            continue;
+        }
+        match register_step(app, shortcut) {
+            Ok(()) => followers.push(shortcut),
+            Err(error) => eprintln!("Failed to register a key sequence step: {}", error),
+        }
+    }
+}
+
+fn unregister_all(app: &AppHandle, state: &RegistryState) {
+    for (binding, outcome) in state.bindings.iter().zip(&state.outcomes) {
+        if outcome.is_ok() {
+            let _ = app.global_shortcut().unregister(binding.shortcut);
+        }
+    }
+    // Chords sharing a leader unregister it more than once; the repeats fail
+    // quietly.
+    for (chord, outcome) in state.chords.iter().zip(&state.chord_outcomes) {
+        if outcome.is_ok() {
+            let _ = app.global_shortcut().unregister(chord.shortcuts[0]);
+        }
+    }
+}
+
+/// Reports hotkeys from a config that was rejected before registration, next
+/// to the current registration failures, so the UI can point at them.
+pub(crate) fn report_rejected(app: &AppHandle, errors: &[HotkeyError]) {
+    let registry = app.state::<HotkeyRegistry>();
+    let state = registry.state.lock().unwrap();
+    let rejected = RegistryState {
+        errors: errors.to_vec(),
+        ..Default::default()
+    };
+    emit_failures(app, state.statuses().into_iter().chain(rejected.statuses()));
+}
+
+fn emit_diagnostics(app: &AppHandle, state: &RegistryState) {
+    emit_failures(app, state.statuses());
+}
+
+fn emit_failures(app: &AppHandle, statuses: impl IntoIterator<Item = HotkeyStatus>) {
+    let failures: Vec<_> = statuses
+        .into_iter()
+        .filter(|status| {
+            matches!(
+                status.state,
+                RegistrationState::Failed { .. } | RegistrationState::Invalid { .. }
+            )
+        })
+        .collect();
+    for failure in &failures {
+        eprintln!(
+            "Hotkey \"{}\" is not active: {:?}",
+            failure.accelerator, failure.state
+        );
+    }
+    let _ = app.emit_to(
+        MAIN_WINDOW_LABEL,
+        HOTKEY_DIAGNOSTICS_EVENT,
+        HotkeyDiagnostics { failures },
+    );
+}
+
+fn trigger(app: &AppHandle, action: &HotkeyAction) {
+    match action {
+        HotkeyAction::View { view } => pipeline::run_preset_for_view(app, *view, None),
+        HotkeyAction::Preset {
+            view, preset_id, ..
+        }
+        | HotkeyAction::Chord {
+            view, preset_id, ..
+        } => pipeline::run_preset_for_view(app, *view, Some(preset_id.clone())),
+        HotkeyAction::Undo => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(error) = history::undo_last_replacement(&app).await {
+                    eprintln!("Failed to undo replacement: {}", error);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HotkeysState, Preset};
+
+    fn state(hotkeys: HotkeysState) -> PersistedState {
+        PersistedState {
+            hotkeys: Some(hotkeys),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn display_strings_become_accelerators() {
+        assert_eq!(normalize_accelerator("Ctrl + Shift + S"), "Ctrl+Shift+S");
+        assert_eq!(normalize_accelerator(" Meta + E "), "Super+E");
+        assert_eq!(normalize_accelerator("  "), "");
+    }
+
+    #[test]
+    fn collects_configured_hotkeys_and_skips_blank_ones() {
+        let bindings = plan(&state(HotkeysState {
+            screenshot: String::new(),
+            input_field: "Ctrl + Q".into(),
+            selection: "Ctrl + E".into(),
+            undo: String::new(),
+        }))
+        .bindings;
+
+        let actions: Vec<_> = bindings
+            .iter()
+            .map(|binding| binding.action.clone())
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                HotkeyAction::View {
+                    view: PresetView::InputField
+                },
+                HotkeyAction::View {
+                    view: PresetView::Selection
+                },
+            ]
+        );
+        assert_eq!(bindings[0].accelerator, "Ctrl+Q");
+    }
+
+    #[test]
+    fn reports_every_invalid_accelerator() {
+        let HotkeyPlan {
+            bindings, errors, ..
+        } = plan(&state(HotkeysState {
+            screenshot: "Ctrl + Shift".into(),
+            input_field: "Ctrl + Q".into(),
+            selection: "Ctrl + Blorp".into(),
+            undo: String::new(),
+        }));
+
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(errors.len(), 2);
+        let HotkeyError::InvalidAccelerator {
+            action,
+            accelerator,
+            ..
+        } = &errors[1]
+        else {
+            panic!("expected an invalid accelerator, got {:?}", errors[1]);
+        };
+        assert_eq!(
+            action,
+            &HotkeyAction::View {
+                view: PresetView::Selection
+            }
+        );
+        assert_eq!(accelerator, "Ctrl+Blorp");
+
+        let serialized = serde_json::to_value(&errors[0]).unwrap();
+        assert_eq!(serialized["kind"], "invalidAccelerator");
+        assert_eq!(serialized["action"]["type"], "view");
+        assert_eq!(serialized["action"]["view"], "screenshot");
+    }
+
+    fn preset(id: &str, name: &str, accelerator: Option<&str>) -> Preset {
+        Preset {
+            id: id.into(),
+            name: name.into(),
+            accelerator: accelerator.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn presets_bind_their_own_accelerators() {
+        let mut state = state(HotkeysState {
+            undo: String::new(),
+            ..Default::default()
+        });
+        state.presets.selection = vec![
+            preset("preset-1", "Translate", Some("Ctrl + Alt + T")),
+            preset("preset-2", "Summarize", None),
+        ];
+
+        let bindings = plan(&state).bindings;
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(
+            bindings[0].action,
+            HotkeyAction::Preset {
+                view: PresetView::Selection,
+                preset_id: "preset-1".into(),
+                preset_name: "Translate".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn duplicate_accelerators_name_both_presets() {
+        let mut state = state(HotkeysState::default());
+        state.presets.input_field = vec![preset("preset-1", "Rewrite", Some("Ctrl + Alt + R"))];
+        state.presets.selection = vec![preset("preset-2", "Reply", Some("Control+Alt+r"))];
+
+        let HotkeyPlan {
+            bindings, errors, ..
+        } = plan(&state);
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(errors.len(), 1);
+        let HotkeyError::Conflict {
+            accelerator,
+            first,
+            second,
+            message,
+        } = &errors[0]
+        else {
+            panic!("expected a conflict, got {:?}", errors[0]);
+        };
+        assert_eq!(accelerator, "Control+Alt+r");
+        assert!(matches!(first, HotkeyAction::Preset { preset_id, .. } if preset_id == "preset-1"));
+        assert!(
+            matches!(second, HotkeyAction::Preset { preset_id, .. } if preset_id == "preset-2")
+        );
+        assert_eq!(
+            message,
+            "preset \"Rewrite\" and preset \"Reply\" both use Control+Alt+r"
+        );
+    }
+
+    #[test]
+    fn statuses_cover_registered_failed_and_rejected_hotkeys() {
+        let mut state = state(HotkeysState {
+            input_field: "Ctrl + Q".into(),
+            selection: "Ctrl + E".into(),
+            undo: "Ctrl + Q".into(),
+            ..Default::default()
+        });
+        state.presets.selection = vec![preset("preset-1", "Translate", Some("Ctrl + Nope"))];
+        let HotkeyPlan {
+            bindings,
+            chords,
+            errors,
+        } = plan(&state);
+        let registry = RegistryState {
+            outcomes: vec![Ok(()), Err("HotKey already registered".into())],
+            chord_outcomes: vec![Ok(()); chords.len()],
+            bindings,
+            chords,
+            errors,
+            paused: false,
+        };
+
+        let states: Vec<_> = registry
+            .statuses()
+            .into_iter()
+            .map(|status| serde_json::to_value(status).unwrap())
+            .collect();
+        assert_eq!(states.len(), 4);
+        assert_eq!(states[0]["state"], "registered");
+        assert_eq!(states[1]["state"], "failed");
+        assert_eq!(states[1]["message"], "HotKey already registered");
+        assert_eq!(states[2]["state"], "invalid");
+        assert_eq!(states[2]["action"]["type"], "undo");
+        assert_eq!(states[2]["error"]["kind"], "conflict");
+        assert_eq!(states[3]["error"]["kind"], "invalidAccelerator");
+        assert_eq!(states[3]["action"]["presetId"], "preset-1");
+    }
+
+    #[test]
+    fn key_sequences_are_normalized_and_must_not_overlap() {
+        let mut state = state(HotkeysState::default());
+        let chord = |id: &str, name: &str, chord: &str| Preset {
+            chord: Some(chord.into()),
+            ..preset(id, name, None)
+        };
+        state.presets.selection = vec![
+            chord("preset-1", "Translate", "Ctrl + Space , T"),
+            chord("preset-2", "Reply", "Ctrl+Space"),
+            chord("preset-3", "Tone", "Ctrl+Space, T, Y"),
+        ];
+
+        let HotkeyPlan { chords, errors, .. } = plan(&state);
+        assert_eq!(chords.len(), 1);
+        assert_eq!(chords[0].chord, "Ctrl+Space, T");
+        assert_eq!(
+            chords[0].shortcuts,
+            vec![
+                "Ctrl+Space".parse::<Shortcut>().unwrap(),
+                "T".parse::<Shortcut>().unwrap()
+            ]
+        );
+        assert!(matches!(
+            &errors[0],
+            HotkeyError::InvalidAccelerator { accelerator, .. } if accelerator == "Ctrl+Space"
+        ));
+        let HotkeyError::Conflict { message, .. } = &errors[1] else {
+            panic!("expected a conflict, got {:?}", errors[1]);
+        };
+        assert_eq!(
+            message,
+            "the key sequence of preset \"Translate\" (Ctrl+Space, T) and the key sequence of preset \"Tone\" (Ctrl+Space, T, Y) overlap, so the shorter one always runs first"
+        );
+    }
+
+    #[test]
+    fn key_sequences_may_not_use_a_hotkey_as_a_step() {
+        let mut state = state(HotkeysState {
+            selection: "Ctrl + Space".into(),
+            undo: String::new(),
+            ..Default::default()
+        });
+        let chord = |id: &str, name: &str, chord: &str| Preset {
+            chord: Some(chord.into()),
+            ..preset(id, name, None)
+        };
+        state.presets.selection = vec![
+            chord("preset-1", "Translate", "Ctrl+Space, T"),
+            chord("preset-2", "Reply", "Ctrl+Alt+R, Ctrl + Space"),
+            chord("preset-3", "Tone", "Ctrl+Alt+R, Y"),
+        ];
+
+        let HotkeyPlan { chords, errors, .. } = plan(&state);
+        assert_eq!(chords.len(), 1);
+        assert_eq!(chords[0].chord, "Ctrl+Alt+R, Y");
+        let messages: Vec<_> = errors.iter().map(HotkeyError::message).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "the Selection hotkey and the key sequence of preset \"Translate\" both use Ctrl+Space",
+                "the Selection hotkey and the key sequence of preset \"Reply\" both use Ctrl+Space",
+            ]
+        );
+    }
+
+    #[test]
+    fn the_input_listener_follows_key_sequences() {
+        let mut state = state(HotkeysState::default());
+        state.presets.selection = vec![Preset {
+            chord: Some("Ctrl+Space, T".into()),
+            ..preset("preset-1", "Translate", None)
+        }];
+        let registry = HotkeyRegistry::default();
+        arm_chords(
+            &registry,
+            &RegistryState {
+                chords: plan(&state).chords,
+                ..Default::default()
+            },
+        );
+        let mut sequence = registry.sequence.lock().unwrap();
+        let step = |accelerator| chords::parse_step(accelerator).unwrap();
+        let now = Instant::now();
+
+        assert!(sequence.press(step("T"), now).is_none());
+        assert!(sequence.followers().is_empty());
+
+        // Pressed straight away, before its shortcut can be registered.
+        assert!(matches!(
+            sequence.press(step("Ctrl+Space"), now),
+            Some(ChordProgress::Pending(_))
+        ));
+        assert_eq!(sequence.followers(), vec!["T".parse::<Shortcut>().unwrap()]);
+        assert!(matches!(
+            sequence.press(step("T"), now),
+            Some(ChordProgress::Completed(HotkeyAction::Chord { preset_id, .. }))
+                if preset_id == "preset-1"
+        ));
+        assert!(sequence.followers().is_empty());
+
+        // Any other key breaks the sequence off and releases the followers.
+        sequence.press(step("Ctrl+Space"), now);
+        assert!(matches!(
+            sequence.press(step("A"), now),
+            Some(ChordProgress::Idle)
+        ));
+        assert!(sequence.followers().is_empty());
+        assert!(sequence.press(step("T"), now).is_none());
+    }
+}
@@ -1,54 +1,127 @@
-use rdev::{listen, Button, Event, EventType};
+use rdev::{listen, Event, EventType};
 use serde::{Deserialize, Serialize};
 use std::{
     fs,
     io::{Error, ErrorKind},
     path::PathBuf,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+use tauri::{
+    AppHandle, Emitter, Listener, Manager, PhysicalPosition, PhysicalSize, State, WindowEvent,
 };
-use tauri::{AppHandle, Emitter, Manager, PhysicalPosition, PhysicalSize, State};
 
-// Track drag state
-#[derive(Clone, Debug)]
-struct DragState {
-    is_pressed: bool,
-    start_x: f64,
-    start_y: f64,
-    has_moved: bool,
-    last_x: f64,
-    last_y: f64,
-}
+mod chords;
+mod clipboard;
+mod electron;
+mod focus;
+mod gestures;
+mod history;
+mod hotkeys;
+mod library;
+mod llm;
+mod output;
+mod pipeline;
+mod placement;
+mod recording;
+mod schema;
+mod secrets;
+mod selection;
+mod simulate;
+mod storage;
+mod streaming;
+mod typing;
 
-impl Default for DragState {
-    fn default() -> Self {
-        Self {
-            is_pressed: false,
-            start_x: 0.0,
-            start_y: 0.0,
-            has_moved: false,
-            last_x: 0.0,
-            last_y: 0.0,
-        }
-    }
-}
+use chords::{ChordStep, Modifiers};
+use gestures::{BehaviorSettings, GestureAction, GestureTracker, WindowState};
+use history::{ReplacementEntry, ReplacementHistory};
+use hotkeys::{HotkeyError, HotkeyRegistry, HotkeyStatus};
+use llm::{CompletionRequest, LlmProvider, ProviderKind};
+use pipeline::PresetRunner;
+use placement::Screen;
+use secrets::{SecretVault, Secrets};
+use storage::JsonFile;
+use streaming::CompletionStreams;
 
 #[derive(Clone, Default)]
 struct OverlayState {
-    overlay_position: Arc<Mutex<Option<(f64, f64)>>>,
+    overlay_bounds: Arc<Mutex<Option<(f64, f64, f64, f64)>>>,
     overlay_visible: Arc<Mutex<bool>>,
     floating_bounds: Arc<Mutex<Option<(f64, f64, f64, f64)>>>,
     floating_visible: Arc<Mutex<bool>>,
+    behavior: Arc<Mutex<BehaviorSettings>>,
+    /// The bubble was shown for a selection the user just made and nothing
+    /// has dismissed it since, so PRIMARY still holds that selection.
+    selection_gesture: Arc<AtomicBool>,
+}
+
+impl OverlayState {
+    fn window_state(&self) -> WindowState {
+        WindowState {
+            overlay_visible: self.overlay_visible.lock().map(|v| *v).unwrap_or(false),
+            overlay_bounds: self.overlay_bounds.lock().map(|v| *v).unwrap_or(None),
+            floating_visible: self.floating_visible.lock().map(|v| *v).unwrap_or(false),
+            floating_bounds: self.floating_bounds.lock().map(|v| *v).unwrap_or(None),
+        }
+    }
+
+    fn behavior(&self) -> BehaviorSettings {
+        self.behavior.lock().map(|v| *v).unwrap_or_default()
+    }
+
+    /// Takes effect from the listener's next event and the next time a
+    /// window is placed.
+    fn set_behavior(&self, behavior: BehaviorSettings) {
+        if let Ok(mut current) = self.behavior.lock() {
+            *current = behavior.clamped();
+        }
+    }
+
+    fn set_overlay_visible(&self, visible: bool) {
+        if let Ok(mut is_visible) = self.overlay_visible.lock() {
+            *is_visible = visible;
+        }
+    }
+
+    /// Whether the preset about to run follows a selection gesture. Only the
+    /// first run after the gesture does.
+    fn take_selection_gesture(&self) -> bool {
+        self.selection_gesture.swap(false, Ordering::SeqCst)
+    }
 }
 
 const PRESETS_STATE_EVENT: &str = "gobuddy://presets-state";
-const FLOATING_PANEL_WIDTH: f64 = 120.0;
-const FLOATING_PANEL_HEIGHT: f64 = 180.0;
+const COMPLETION_STREAM_EVENT: &str = "gobuddy://completion-stream";
+const PRESET_SELECTED_EVENT: &str = "gobuddy://preset-selected";
+const MAIN_WINDOW_LABEL: &str = "main";
+const RESULT_WINDOW_LABEL: &str = "result_window";
+const MAX_TEMPERATURE: f32 = 2.0;
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
 struct Preset {
     id: String,
     name: String,
     prompt: String,
+    // Generation overrides; unset fields fall back to `SettingsState`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    // Inline output mode; unset falls back to app rules and then settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output_mode: Option<output::OutputMode>,
+    // Global hotkey that runs this preset directly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    accelerator: Option<String>,
+    // Key sequence, such as "Ctrl+Space, T", that runs this preset directly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    chord: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -61,6 +134,58 @@ struct PresetCollection {
     selection: Vec<Preset>,
 }
 
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+enum PresetView {
+    #[serde(rename = "screenshot")]
+    Screenshot,
+    #[serde(rename = "inputField")]
+    InputField,
+    #[serde(rename = "selection")]
+    Selection,
+}
+
+impl PresetView {
+    const ALL: [PresetView; 3] = [
+        PresetView::Screenshot,
+        PresetView::InputField,
+        PresetView::Selection,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            PresetView::Screenshot => "Screenshot",
+            PresetView::InputField => "Input Field",
+            PresetView::Selection => "Selection",
+        }
+    }
+}
+
+impl PresetCollection {
+    fn for_view(&self, view: PresetView) -> &[Preset] {
+        match view {
+            PresetView::Screenshot => &self.screenshot,
+            PresetView::InputField => &self.input_field,
+            PresetView::Selection => &self.selection,
+        }
+    }
+
+    fn for_view_mut(&mut self, view: PresetView) -> &mut Vec<Preset> {
+        match view {
+            PresetView::Screenshot => &mut self.screenshot,
+            PresetView::InputField => &mut self.input_field,
+            PresetView::Selection => &mut self.selection,
+        }
+    }
+
+    fn find(&self, preset_id: &str) -> Option<&Preset> {
+        self.screenshot
+            .iter()
+            .chain(self.input_field.iter())
+            .chain(self.selection.iter())
+            .find(|preset| preset.id == preset_id)
+    }
+}
+
 impl Default for PresetCollection {
     fn default() -> Self {
         Self {
@@ -81,6 +206,24 @@ struct ActivePresetIds {
     selection: Option<String>,
 }
 
+impl ActivePresetIds {
+    fn for_view(&self, view: PresetView) -> Option<&str> {
+        match view {
+            PresetView::Screenshot => self.screenshot.as_deref(),
+            PresetView::InputField => self.input_field.as_deref(),
+            PresetView::Selection => self.selection.as_deref(),
+        }
+    }
+
+    fn for_view_mut(&mut self, view: PresetView) -> &mut Option<String> {
+        match view {
+            PresetView::Screenshot => &mut self.screenshot,
+            PresetView::InputField => &mut self.input_field,
+            PresetView::Selection => &mut self.selection,
+        }
+    }
+}
+
 impl Default for ActivePresetIds {
     fn default() -> Self {
         Self {
@@ -96,12 +239,85 @@ impl Default for ActivePresetIds {
 struct SettingsState {
     auto_open_on_start: bool,
     openai_api_key: String,
+    #[serde(default)]
+    openai_base_url: String,
+    #[serde(default)]
+    provider: ProviderKind,
+    #[serde(default)]
+    providers: llm::ProviderSettings,
+    #[serde(default)]
+    temperature: Option<f32>,
+    #[serde(default)]
+    max_tokens: Option<u32>,
+    #[serde(default)]
+    output: output::OutputSettings,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+impl SettingsState {
+    /// Configuration for a provider. OpenAI keeps reading the top-level key and
+    /// base URL that the settings page already edits.
+    fn provider_config(&self, kind: ProviderKind) -> llm::ProviderConfig {
+        let mut config = self.providers.get(kind).clone();
+        if kind == ProviderKind::OpenAi {
+            if config.api_key.trim().is_empty() {
+                config.api_key = self.openai_api_key.clone();
+            }
+            if config.base_url.trim().is_empty() {
+                config.base_url = self.openai_base_url.clone();
+            }
+        }
+        config
+    }
+
+    /// JSON pointers to the API keys within the serialized settings, in the
+    /// order `api_keys_mut` yields them.
+    const API_KEYS: [&'static str; 5] = [
+        "/openaiApiKey",
+        "/providers/openai/apiKey",
+        "/providers/anthropic/apiKey",
+        "/providers/ollama/apiKey",
+        "/providers/openaiCompatible/apiKey",
+    ];
+
+    /// Every API key, named as in `API_KEYS`. These are what the secret vault
+    /// holds in place of the state file.
+    fn api_keys_mut(&mut self) -> impl Iterator<Item = (&'static str, &mut String)> {
+        let providers = &mut self.providers;
+        Self::API_KEYS.into_iter().zip([
+            &mut self.openai_api_key,
+            &mut providers.openai.api_key,
+            &mut providers.anthropic.api_key,
+            &mut providers.ollama.api_key,
+            &mut providers.openai_compatible.api_key,
+        ])
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct HotkeysState {
     screenshot: String,
+    #[serde(default)]
+    input_field: String,
+    #[serde(default)]
+    selection: String,
+    #[serde(default = "default_undo_hotkey")]
+    undo: String,
+}
+
+impl Default for HotkeysState {
+    fn default() -> Self {
+        Self {
+            screenshot: String::new(),
+            input_field: String::new(),
+            selection: String::new(),
+            undo: default_undo_hotkey(),
+        }
+    }
+}
+
+fn default_undo_hotkey() -> String {
+    history::DEFAULT_UNDO_HOTKEY.to_string()
 }
 
 fn default_next_preset_id() -> i32 {
@@ -111,6 +327,9 @@ fn default_next_preset_id() -> i32 {
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 struct PersistedState {
+    // Stamped with `schema::SCHEMA_VERSION` on every save.
+    #[serde(default)]
+    schema_version: u64,
     #[serde(default)]
     presets: PresetCollection,
     #[serde(default = "default_next_preset_id")]
@@ -119,28 +338,58 @@ struct PersistedState {
     active_preset_ids: ActivePresetIds,
     settings: Option<SettingsState>,
     hotkeys: Option<HotkeysState>,
+    #[serde(default)]
+    behavior: BehaviorSettings,
 }
 
+impl PersistedState {
+    /// Moves the non-empty API keys out, leaving empty strings behind.
+    fn take_secrets(&mut self) -> Secrets {
+        let Some(settings) = self.settings.as_mut() else {
+            return Secrets::new();
+        };
+        settings
+            .api_keys_mut()
+            .filter(|(_, key)| !key.is_empty())
+            .map(|(name, key)| (name.to_string(), std::mem::take(key)))
+            .collect()
+    }
+
+    /// Fills in API keys from `secrets`, replacing whatever the state holds.
+    fn restore_secrets(&mut self, secrets: &Secrets) {
+        if let Some(settings) = self.settings.as_mut() {
+            for (name, key) in settings.api_keys_mut() {
+                *key = secrets.get(name).cloned().unwrap_or_default();
+            }
+        }
+    }
+
+    /// The state as webviews may see it, with API keys masked.
+    fn masked(mut self) -> Self {
+        if let Some(settings) = self.settings.as_mut() {
+            for (_, key) in settings.api_keys_mut() {
+                *key = secrets::mask(key);
+            }
+        }
+        self
+    }
+}
+
+/// The saved state. API keys are kept in the secret vault; states going in
+/// and out of the store carry them in full, and only the file on disk has
+/// them blanked.
 struct PresetStateStore {
-    path: PathBuf,
+    file: JsonFile,
+    vault: SecretVault,
     cache: Mutex<Option<PersistedState>>,
 }
 
 impl PresetStateStore {
-    fn new(path: PathBuf, initial: Option<PersistedState>) -> Self {
+    fn new(file: JsonFile, vault: SecretVault) -> Self {
         Self {
-            path,
-            cache: Mutex::new(initial),
-        }
-    }
-
-    fn load_from_disk(path: &PathBuf) -> Result<Option<PersistedState>, String> {
-        match fs::read_to_string(path) {
-            Ok(contents) => serde_json::from_str(&contents)
-                .map(Some)
-                .map_err(|err| err.to_string()),
-            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
-            Err(error) => Err(error.to_string()),
+            file,
+            vault,
+            cache: Mutex::new(None),
         }
     }
 
@@ -152,25 +401,78 @@ impl PresetStateStore {
             }
         }
 
-        let loaded = Self::load_from_disk(&self.path)?;
+        let mut loaded: Option<PersistedState> = self.file.load()?;
+        if let Some(state) = loaded.as_mut() {
+            let mut secrets = self.load_secrets();
+            let plain = state.take_secrets();
+            if !plain.is_empty() {
+                self.move_into_vault(state, &mut secrets, plain)?;
+            }
+            state.restore_secrets(&secrets);
+        }
         let mut cache = self.cache.lock().map_err(|err| err.to_string())?;
         *cache = loaded.clone();
         Ok(loaded)
     }
 
-    fn save_state(&self, state: PersistedState) -> Result<PersistedState, String> {
-        if let Some(parent) = self.path.parent() {
-            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    /// Saves `state`. A masked API key, as the UI sends back an unchanged
+    /// one, keeps the key already stored.
+    fn save_state(&self, mut state: PersistedState) -> Result<PersistedState, String> {
+        state.schema_version = schema::SCHEMA_VERSION;
+        let stored = self.load_secrets();
+        if let Some(settings) = state.settings.as_mut() {
+            for (name, key) in settings.api_keys_mut() {
+                if secrets::is_masked(key) {
+                    *key = stored.get(name).cloned().unwrap_or_default();
+                }
+            }
         }
 
-        let serialized = serde_json::to_string_pretty(&state).map_err(|err| err.to_string())?;
-        fs::write(&self.path, serialized).map_err(|err| err.to_string())?;
+        let mut on_disk = state.clone();
+        let secrets = on_disk.take_secrets();
+        if secrets != stored {
+            self.vault.save(&secrets)?;
+        }
+        self.file.save(&on_disk)?;
 
         let mut cache = self.cache.lock().map_err(|err| err.to_string())?;
         *cache = Some(state.clone());
 
         Ok(state)
     }
+
+    /// The vault's contents. One that can't be read, say after the data
+    /// folder was copied from another machine, is treated as empty and
+    /// replaced on the next save.
+    fn load_secrets(&self) -> Secrets {
+        self.vault.load().unwrap_or_else(|error| {
+            eprintln!(
+                "Failed to read the secret vault, API keys need to be entered again: {}",
+                error
+            );
+            Secrets::new()
+        })
+    }
+
+    /// Moves API keys that an older build saved in plain text into the vault,
+    /// and blanks them in the state file and its backups. Copies that can't
+    /// be read, and so can't be blanked, are deleted.
+    fn move_into_vault(
+        &self,
+        state: &PersistedState,
+        secrets: &mut Secrets,
+        plain: Secrets,
+    ) -> Result<(), String> {
+        secrets.extend(plain);
+        self.vault.save(secrets)?;
+        self.file.save(state)?;
+        let pointers = SettingsState::API_KEYS.map(|name| format!("/settings{}", name));
+        self.file.rewrite_backups(|document| {
+            secrets::scrub(document, pointers.iter().map(String::as_str))
+        })?;
+        eprintln!("Moved API keys from the preset state into the secret vault");
+        Ok(())
+    }
 }
 
 fn ensure_overlay_window(app: &AppHandle) {
@@ -184,9 +486,8 @@ fn ensure_overlay_window(app: &AppHandle) {
         tauri::WebviewUrl::App("overlay.html".into()),
     )
     .title("Overlay")
+    // Resized to the configured bubble size each time it is shown.
     .inner_size(32.0, 32.0)
-    .min_inner_size(32.0, 32.0)
-    .max_inner_size(32.0, 32.0)
     .position(0.0, 0.0)
     .decorations(false)
     .transparent(true)
@@ -209,7 +510,10 @@ fn ensure_floating_window(app: &AppHandle) {
         tauri::WebviewUrl::App("floating-window.html".into()),
     )
     .title("GoBuddy Quick Panel")
-    .inner_size(FLOATING_PANEL_WIDTH, FLOATING_PANEL_HEIGHT)
+    .inner_size(
+        BehaviorSettings::default().panel_width,
+        BehaviorSettings::default().panel_height,
+    )
     .resizable(false)
     .skip_taskbar(true)
     .always_on_top(true)
@@ -221,15 +525,78 @@ fn ensure_floating_window(app: &AppHandle) {
     .build();
 }
 
-fn primary_monitor_dimensions(app: &AppHandle) -> (f64, f64) {
-    app.primary_monitor()
-        .ok()
-        .flatten()
-        .map(|monitor| {
-            let size = monitor.size();
-            (size.width as f64, size.height as f64)
-        })
-        .unwrap_or((1920.0, 1080.0))
+fn ensure_result_window(app: &AppHandle) {
+    if app.get_webview_window(RESULT_WINDOW_LABEL).is_some() {
+        return;
+    }
+
+    let _ = tauri::WebviewWindowBuilder::new(
+        app,
+        RESULT_WINDOW_LABEL,
+        tauri::WebviewUrl::App("result-window.html".into()),
+    )
+    .title("GoBuddy Result")
+    .inner_size(360.0, 240.0)
+    .resizable(true)
+    .skip_taskbar(true)
+    .always_on_top(true)
+    .decorations(false)
+    .shadow(true)
+    .visible(false)
+    .build();
+}
+
+fn show_result_window(app: &AppHandle) -> Result<(), String> {
+    ensure_result_window(app);
+    if let Some(window) = app.get_webview_window(RESULT_WINDOW_LABEL) {
+        let _ = window.center();
+        window.show().map_err(|e| e.to_string())?;
+        let _ = window.set_focus();
+    }
+    Ok(())
+}
+
+// Used when the platform reports no monitors at all.
+const FALLBACK_SCREEN: Screen = Screen {
+    bounds: (0.0, 0.0, 1920.0, 1080.0),
+    work_area: (0.0, 0.0, 1920.0, 1080.0),
+    scale_factor: 1.0,
+};
+
+/// The monitor containing `cursor` (rdev coordinates), or the nearest one.
+fn screen_at(app: &AppHandle, cursor: (f64, f64)) -> Screen {
+    let screens: Vec<Screen> = match app.available_monitors() {
+        Ok(monitors) => monitors
+            .iter()
+            .map(|monitor| {
+                let position = monitor.position();
+                let size = monitor.size();
+                let work_area = monitor.work_area();
+                Screen {
+                    bounds: (
+                        position.x as f64,
+                        position.y as f64,
+                        size.width as f64,
+                        size.height as f64,
+                    ),
+                    work_area: (
+                        work_area.position.x as f64,
+                        work_area.position.y as f64,
+                        work_area.size.width as f64,
+                        work_area.size.height as f64,
+                    ),
+                    scale_factor: monitor.scale_factor(),
+                }
+            })
+            .collect(),
+        Err(err) => {
+            eprintln!("Failed to list monitors: {}", err);
+            Vec::new()
+        }
+    };
+    placement::screen_at(&screens, cursor)
+        .copied()
+        .unwrap_or(FALLBACK_SCREEN)
 }
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
@@ -242,7 +609,38 @@ fn greet(name: &str) -> String {
 fn load_presets_state(
     preset_store: State<'_, PresetStateStore>,
 ) -> Result<Option<PersistedState>, String> {
-    preset_store.load_state()
+    Ok(preset_store.load_state()?.map(PersistedState::masked))
+}
+
+/// Sends `state` to every webview, with API keys masked.
+fn emit_state(app: &AppHandle, state: &PersistedState) -> Result<(), String> {
+    app.emit(PRESETS_STATE_EVENT, state.clone().masked())
+        .map_err(|err| err.to_string())
+}
+
+/// Upgrades a state written by an older build, such as the webview's
+/// localStorage copy, to the current shape without saving it. API keys come
+/// back as the webview sent them, so a key only that copy holds reaches the
+/// vault on the next save.
+#[tauri::command]
+fn migrate_presets_state(state: serde_json::Value) -> Result<PersistedState, String> {
+    serde_json::from_value(schema::migrate(state)?).map_err(|err| err.to_string())
+}
+
+/// Why `save_presets_state` rejected a state, tagged so the UI can point at
+/// the offending hotkeys or behavior values.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum SaveStateError {
+    InvalidHotkeys { errors: Vec<HotkeyError> },
+    InvalidBehavior { errors: Vec<String> },
+    Storage { message: String },
+}
+
+impl From<String> for SaveStateError {
+    fn from(message: String) -> Self {
+        Self::Storage { message }
+    }
 }
 
 #[tauri::command]
@@ -250,10 +648,282 @@ fn save_presets_state(
     app: AppHandle,
     preset_store: State<'_, PresetStateStore>,
     state: PersistedState,
-) -> Result<(), String> {
+) -> Result<(), SaveStateError> {
+    // Validate hotkeys first so a typo never gets persisted.
+    let plan = hotkeys::plan(&state);
+    if !plan.errors.is_empty() {
+        hotkeys::report_rejected(&app, &plan.errors);
+        return Err(SaveStateError::InvalidHotkeys {
+            errors: plan.errors,
+        });
+    }
+    state
+        .behavior
+        .validate()
+        .map_err(|errors| SaveStateError::InvalidBehavior { errors })?;
     let saved = preset_store.save_state(state)?;
-    app.emit(PRESETS_STATE_EVENT, saved)
-        .map_err(|err| err.to_string())
+    hotkeys::apply(&app, plan);
+    app.state::<OverlayState>().set_behavior(saved.behavior);
+    emit_state(&app, &saved).map_err(SaveStateError::from)
+}
+
+/// What an import did, and the state it saved with API keys masked.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StateImport {
+    report: library::ImportReport,
+    state: PersistedState,
+}
+
+/// Saves the result of an import and puts its hotkeys into effect. Imports
+/// check the hotkeys they add one by one, so the plan is as clean as the state
+/// was before.
+fn save_import(
+    app: &AppHandle,
+    preset_store: &PresetStateStore,
+    report: library::ImportReport,
+    state: PersistedState,
+) -> Result<StateImport, String> {
+    if report.imported.is_empty() {
+        return Ok(StateImport {
+            report,
+            state: state.masked(),
+        });
+    }
+    let plan = hotkeys::plan(&state);
+    let saved = preset_store.save_state(state)?;
+    hotkeys::apply(app, plan);
+    emit_state(app, &saved)?;
+    Ok(StateImport {
+        report,
+        state: saved.masked(),
+    })
+}
+
+/// Imports the Electron build's presets, hotkeys and settings from `files`,
+/// or from its data folder when no files are given.
+#[tauri::command]
+fn import_electron_config(
+    app: AppHandle,
+    preset_store: State<'_, PresetStateStore>,
+    files: Option<Vec<String>>,
+) -> Result<StateImport, String> {
+    let paths: Vec<PathBuf> = match files {
+        Some(files) if !files.is_empty() => files.into_iter().map(PathBuf::from).collect(),
+        _ => {
+            let config_dir = app.path().config_dir().map_err(|err| err.to_string())?;
+            electron::find_config_files(&config_dir)
+        }
+    };
+    if paths.is_empty() {
+        return Err("No configuration from the Electron version of GoBuddy was found".into());
+    }
+
+    let mut state = preset_store.load_state()?.unwrap_or_default();
+    let report = electron::import(&mut state, &paths);
+    save_import(&app, &preset_store, report, state)
+}
+
+/// Writes the presets in `preset_ids`, or all of them, to `path` as a preset
+/// library. The format follows the extension unless `format` is given.
+/// Returns how many presets were written.
+#[tauri::command]
+fn export_presets(
+    preset_store: State<'_, PresetStateStore>,
+    path: String,
+    format: Option<library::LibraryFormat>,
+    preset_ids: Option<Vec<String>>,
+) -> Result<usize, String> {
+    let path = PathBuf::from(path);
+    let format = match format {
+        Some(format) => format,
+        None => library::LibraryFormat::from_path(&path)?,
+    };
+    let state = preset_store.load_state()?.unwrap_or_default();
+    let exported = library::export(&state.presets, preset_ids.as_deref())?;
+    library::write(&path, format, &exported)?;
+    Ok(PresetView::ALL
+        .into_iter()
+        .map(|view| exported.presets.for_view(view).len())
+        .sum())
+}
+
+/// Merges the preset library at `path` into the saved presets.
+#[tauri::command]
+fn import_presets(
+    app: AppHandle,
+    preset_store: State<'_, PresetStateStore>,
+    path: String,
+    format: Option<library::LibraryFormat>,
+    options: Option<library::ImportOptions>,
+) -> Result<StateImport, String> {
+    let path = PathBuf::from(path);
+    let format = match format {
+        Some(format) => format,
+        None => library::LibraryFormat::from_path(&path)?,
+    };
+    let imported = library::read(&path, format)?;
+    let mut state = preset_store.load_state()?.unwrap_or_default();
+    let mut report = library::ImportReport {
+        sources: vec![path.display().to_string()],
+        ..Default::default()
+    };
+    library::merge(
+        &mut state,
+        imported,
+        options.unwrap_or_default(),
+        &mut report,
+    );
+    save_import(&app, &preset_store, report, state)
+}
+
+#[tauri::command]
+fn set_hotkeys_paused(app: AppHandle, paused: bool) {
+    hotkeys::set_paused(&app, paused);
+}
+
+#[tauri::command]
+fn get_hotkey_status(app: AppHandle) -> Vec<HotkeyStatus> {
+    hotkeys::statuses(&app)
+}
+
+#[tauri::command]
+fn list_replacement_history(history: State<'_, ReplacementHistory>) -> Vec<ReplacementEntry> {
+    history.entries()
+}
+
+#[tauri::command]
+async fn undo_last_replacement(app: AppHandle) -> Result<ReplacementEntry, String> {
+    history::undo_last_replacement(&app).await
+}
+
+/// Resolves a preset and builds the provider and request needed to run it.
+fn prepare_preset_completion(
+    preset_store: &PresetStateStore,
+    preset_id: &str,
+    input: &str,
+) -> Result<(Box<dyn LlmProvider>, CompletionRequest), String> {
+    let state = preset_store.load_state()?.unwrap_or_default();
+    let preset = state
+        .presets
+        .find(preset_id)
+        .cloned()
+        .ok_or_else(|| format!("Preset \"{}\" not found", preset_id))?;
+    prepare_completion(&preset, &state.settings.unwrap_or_default(), input)
+}
+
+fn prepare_completion(
+    preset: &Preset,
+    settings: &SettingsState,
+    input: &str,
+) -> Result<(Box<dyn LlmProvider>, CompletionRequest), String> {
+    let config = settings.provider_config(settings.provider);
+    let provider = llm::build_provider(settings.provider, &config)?;
+    let request = build_completion_request(preset, settings, &config, input)?;
+    Ok((provider, request))
+}
+
+/// Layers a preset's generation overrides over the global defaults.
+fn build_completion_request(
+    preset: &Preset,
+    settings: &SettingsState,
+    config: &llm::ProviderConfig,
+    input: &str,
+) -> Result<CompletionRequest, String> {
+    let model = preset
+        .model
+        .as_deref()
+        .map(str::trim)
+        .filter(|model| !model.is_empty())
+        .unwrap_or_else(|| config.model_or_default(settings.provider))
+        .to_string();
+
+    let temperature = preset.temperature.or(settings.temperature);
+    if let Some(value) = temperature {
+        if !(0.0..=MAX_TEMPERATURE).contains(&value) {
+            return Err(format!(
+                "Temperature for \"{}\" must be between 0 and {}",
+                preset.name, MAX_TEMPERATURE
+            ));
+        }
+    }
+
+    let max_tokens = preset.max_tokens.or(settings.max_tokens);
+    if max_tokens == Some(0) {
+        return Err(format!(
+            "Max tokens for \"{}\" must be greater than 0",
+            preset.name
+        ));
+    }
+
+    Ok(CompletionRequest {
+        model,
+        messages: llm::build_preset_messages(&preset.prompt, input),
+        temperature,
+        max_tokens,
+    })
+}
+
+#[tauri::command]
+async fn run_preset(
+    preset_store: State<'_, PresetStateStore>,
+    preset_id: String,
+    input: String,
+) -> Result<String, String> {
+    let (provider, request) = prepare_preset_completion(&preset_store, &preset_id, &input)?;
+    provider.complete(&request).await
+}
+
+/// Lists the models a provider reports, defaulting to the selected provider.
+#[tauri::command]
+async fn list_provider_models(
+    preset_store: State<'_, PresetStateStore>,
+    provider: Option<ProviderKind>,
+) -> Result<Vec<String>, String> {
+    let settings = preset_store
+        .load_state()?
+        .and_then(|state| state.settings)
+        .unwrap_or_default();
+    let kind = provider.unwrap_or(settings.provider);
+    let backend = llm::build_provider(kind, &settings.provider_config(kind))?;
+    backend.list_models().await
+}
+
+#[tauri::command]
+fn stream_preset(
+    app: AppHandle,
+    preset_store: State<'_, PresetStateStore>,
+    streams: State<'_, CompletionStreams>,
+    preset_id: String,
+    input: String,
+) -> Result<u64, String> {
+    let (provider, request) = prepare_preset_completion(&preset_store, &preset_id, &input)?;
+    show_result_window(&app)?;
+    Ok(streams.start(&app, preset_id, provider, request))
+}
+
+#[tauri::command]
+fn cancel_completion_stream(
+    app: AppHandle,
+    streams: State<'_, CompletionStreams>,
+    request_id: Option<u64>,
+) -> bool {
+    match request_id {
+        Some(request_id) => streams.cancel(&app, request_id),
+        None => {
+            streams.cancel_all(&app);
+            true
+        }
+    }
+}
+
+#[tauri::command]
+fn hide_result_window(app: AppHandle, streams: State<'_, CompletionStreams>) -> Result<(), String> {
+    streams.cancel_all(&app);
+    if let Some(window) = app.get_webview_window(RESULT_WINDOW_LABEL) {
+        window.hide().map_err(|e| e.to_string())?;
+    }
+    Ok(())
 }
 
 fn emit_latest_presets_state(app: &AppHandle) {
@@ -271,7 +941,7 @@ fn emit_latest_presets_state(app: &AppHandle) {
         }
     };
 
-    if let Err(error) = app.emit(PRESETS_STATE_EVENT, current_state) {
+    if let Err(error) = emit_state(app, &current_state) {
         eprintln!("Failed to emit latest presets state: {}", error);
     }
 }
@@ -311,29 +981,31 @@ async fn hide_floating_window(app: AppHandle, overlay_state: State<'_, OverlaySt
 
 fn show_or_focus_floating_window(app: &AppHandle, overlay_state: &OverlayState) -> Result<(), String> {
     ensure_floating_window(app);
-    let (overlay_x, overlay_y) = match overlay_state.overlay_position.lock() {
-        Ok(stored) => stored.clone().unwrap_or((200.0, 200.0)),
-        Err(_) => (200.0, 200.0),
+    let (overlay_x, overlay_y, overlay_w, overlay_h) = match overlay_state.overlay_bounds.lock() {
+        Ok(stored) => stored.unwrap_or((200.0, 200.0, 0.0, 0.0)),
+        Err(_) => (200.0, 200.0, 0.0, 0.0),
     };
 
-    let panel_width_f = FLOATING_PANEL_WIDTH;
-    let panel_height_f = FLOATING_PANEL_HEIGHT;
-    let panel_width = panel_width_f.round() as u32;
-    let panel_height = panel_height_f.round() as u32;
-    let panel_center_x = overlay_x + 16.0;
-    let (screen_w, screen_h) = primary_monitor_dimensions(app);
-    let max_x = (screen_w - panel_width_f).max(0.0);
-    let max_y = (screen_h - panel_height_f).max(0.0);
-    let panel_x = (panel_center_x - panel_width_f / 2.0).clamp(0.0, max_x);
-    let panel_y = overlay_y.clamp(0.0, max_y);
+    // Open on the bubble's monitor, laid out in its logical pixels.
+    let behavior = overlay_state.behavior();
+    let screen = screen_at(
+        app,
+        (overlay_x + overlay_w / 2.0, overlay_y + overlay_h / 2.0),
+    );
+    let origin = behavior.panel_origin(screen.local((overlay_x, overlay_y)));
+    let placement = screen.place(origin, (behavior.panel_width, behavior.panel_height));
+    let (panel_x, panel_y) = placement.position;
+    let (panel_width, panel_height) = placement.size;
 
     if let Some(window) = app.get_webview_window("floating_panel") {
-        let _ = window.set_position(PhysicalPosition::new(panel_x.round() as i32, panel_y.round() as i32));
+        // Move before resizing: crossing onto a monitor with another scale
+        // factor rescales the window.
+        let _ = window.set_position(PhysicalPosition::new(panel_x, panel_y));
         let _ = window.set_size(PhysicalSize::new(panel_width, panel_height));
         let _ = window.set_always_on_top(true);
         window.show().map_err(|e| e.to_string())?;
         if let Ok(mut bounds) = overlay_state.floating_bounds.lock() {
-            *bounds = Some((panel_x, panel_y, panel_width_f, panel_height_f));
+            *bounds = Some(placement.cursor_bounds);
         }
         if let Ok(mut visible) = overlay_state.floating_visible.lock() {
             *visible = true;
@@ -356,30 +1028,31 @@ fn show_overlay_at_position(app: &AppHandle, overlay_state: &OverlayState, x: f6
 
     tauri::async_runtime::spawn(async move {
         ensure_overlay_window(&app_handle);
-        // Determine offset direction based on drag direction
-        // If dragged to the left (end_x < start_x), show button to the left
-        let x_offset = if x < start_x { -50.0 } else { 10.0 };
-        let raw_overlay_x = x + x_offset;
-        let raw_overlay_y = y + 10.0;
-        let (screen_w, screen_h) = primary_monitor_dimensions(&app_handle);
-        let max_x = (screen_w - 32.0).max(0.0);
-        let max_y = (screen_h - 32.0).max(0.0);
-        let overlay_x = raw_overlay_x.clamp(0.0, max_x);
-        let overlay_y = raw_overlay_y.clamp(0.0, max_y);
-        
+        // Lay the bubble out in the logical pixels of the monitor under the
+        // cursor, then hand Tauri physical pixels.
+        let behavior = overlay_state.behavior();
+        let size = behavior.overlay_size;
+        let screen = screen_at(&app_handle, (x, y));
+        let (local_x, local_y) = screen.local((x, y));
+        let (local_start_x, _) = screen.local((start_x, y));
+        let origin = behavior.overlay_origin(local_x, local_y, local_start_x);
+        let placement = screen.place(origin, (size, size));
+        let (overlay_x, overlay_y) = placement.position;
+        let (overlay_w, overlay_h) = placement.size;
+
         match app_handle.get_webview_window("overlay") {
             Some(window) => {
                 // Position the window near the mouse cursor
                 // Offset slightly so button appears next to cursor, not under it
-                let _ = window.set_size(PhysicalSize::new(32, 32));
-                let _ = window.set_position(PhysicalPosition::new(overlay_x.round() as i32, overlay_y.round() as i32));
+                let _ = window.set_position(PhysicalPosition::new(overlay_x, overlay_y));
+                let _ = window.set_size(PhysicalSize::new(overlay_w, overlay_h));
                 let _ = window.show();
             }
             None => {}
         }
 
-        if let Ok(mut stored) = overlay_state.overlay_position.lock() {
-            *stored = Some((overlay_x, overlay_y));
+        if let Ok(mut stored) = overlay_state.overlay_bounds.lock() {
+            *stored = Some(placement.cursor_bounds);
         }
         if let Ok(mut visible) = overlay_state.overlay_visible.lock() {
             *visible = true;
@@ -387,151 +1060,97 @@ fn show_overlay_at_position(app: &AppHandle, overlay_state: &OverlayState, x: f6
     });
 }
 
+fn apply_gesture_action(app: &AppHandle, overlay_state: &OverlayState, action: GestureAction) {
+    let app_clone = app.clone();
+    let overlay_state_clone = overlay_state.clone();
+    match action {
+        GestureAction::ShowOverlay { x, y, start_x } => {
+            // Flip the flag now so the next events see the overlay as shown.
+            overlay_state.set_overlay_visible(true);
+            overlay_state
+                .selection_gesture
+                .store(true, Ordering::SeqCst);
+            show_overlay_at_position(app, overlay_state, x, y, start_x);
+        }
+        GestureAction::HideOverlay => {
+            overlay_state.set_overlay_visible(false);
+            overlay_state
+                .selection_gesture
+                .store(false, Ordering::SeqCst);
+            tauri::async_runtime::spawn(async move {
+                let _ = hide_overlay_internal(&app_clone, &overlay_state_clone).await;
+            });
+        }
+        GestureAction::HideFloating => {
+            overlay_state
+                .selection_gesture
+                .store(false, Ordering::SeqCst);
+            tauri::async_runtime::spawn(async move {
+                let _ = hide_floating_window_internal(&app_clone, &overlay_state_clone).await;
+            });
+        }
+        GestureAction::OpenPanel => {
+            tauri::async_runtime::spawn(async move {
+                if let Err(error) = show_or_focus_floating_window(&app_clone, &overlay_state_clone)
+                {
+                    eprintln!("Failed to open floating window: {}", error);
+                }
+                let _ = hide_overlay_internal(&app_clone, &overlay_state_clone).await;
+            });
+        }
+    }
+}
+
 fn start_mouse_listener(app: AppHandle, overlay_state: OverlayState) {
-    let drag_state = Arc::new(Mutex::new(DragState::default()));
-    let overlay_visible = overlay_state.overlay_visible.clone();
-    let overlay_position = overlay_state.overlay_position.clone();
-    let floating_visible = overlay_state.floating_visible.clone();
-    let floating_bounds = overlay_state.floating_bounds.clone();
-    let overlay_click_in_progress = Arc::new(Mutex::new(false));
-    
     std::thread::spawn(move || {
+        let mut tracker = GestureTracker::default();
+        let mut recorder = recording::EventRecorder::from_env();
+        let mut modifiers = Modifiers::default();
         let callback = move |event: Event| {
-            let mut state = drag_state.lock().unwrap();
-            
-            match event.event_type {
-                EventType::ButtonPress(Button::Left) => {
-                    let cursor_x = state.last_x;
-                    let cursor_y = state.last_y;
-
-                    let floating_is_visible = floating_visible.lock().map(|v| *v).unwrap_or(false);
-                    let inside_floating_window = if floating_is_visible {
-                        if let Ok(bounds_guard) = floating_bounds.lock() {
-                            if let Some((fx, fy, fw, fh)) = *bounds_guard {
-                                cursor_x >= fx && cursor_x <= fx + fw && cursor_y >= fy && cursor_y <= fy + fh
-                            } else {
-                                false
-                            }
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
-                    };
-
-                    if floating_is_visible && !inside_floating_window {
-                        let app_clone = app.clone();
-                        let overlay_state_clone = overlay_state.clone();
-                        tauri::async_runtime::spawn(async move {
-                            let _ = hide_floating_window_internal(&app_clone, &overlay_state_clone).await;
-                        });
-                    }
-
-                    if floating_is_visible {
-                        state.is_pressed = false;
-                        state.has_moved = false;
-                        return;
-                    }
-
-                    let overlay_is_visible = overlay_visible.lock().map(|v| *v).unwrap_or(false);
-                    let inside_overlay = if overlay_is_visible {
-                        if let Ok(overlay_xy) = overlay_position.lock() {
-                            if let Some((ox, oy)) = *overlay_xy {
-                                cursor_x >= ox
-                                    && cursor_x <= ox + 32.0
-                                    && cursor_y >= oy
-                                    && cursor_y <= oy + 32.0
-                            } else {
-                                false
-                            }
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
-                    };
+            // Our own copy, paste, typing and undo come back through here;
+            // left in, they would look like the user selecting or typing.
+            // Only keys are simulated, so pointer events always get through.
+            let is_key = matches!(
+                event.event_type,
+                EventType::KeyPress(_) | EventType::KeyRelease(_)
+            );
+            if is_key && simulate::is_simulating() {
+                return;
+            }
 
-                    if inside_overlay {
-                        if let Ok(mut clicking_overlay) = overlay_click_in_progress.lock() {
-                            *clicking_overlay = true;
-                        }
-                    } else if overlay_is_visible {
-                        if let Ok(mut is_overlay_visible) = overlay_visible.lock() {
-                            *is_overlay_visible = false;
-                        }
-                        let app_clone = app.clone();
-                        let overlay_state_clone = overlay_state.clone();
-                        tauri::async_runtime::spawn(async move {
-                            let _ = hide_overlay_internal(&app_clone, &overlay_state_clone).await;
-                        });
-                    }
-                    
-                    // Start tracking drag or click - use current tracked position
-                    state.is_pressed = true;
-                    state.start_x = state.last_x;
-                    state.start_y = state.last_y;
-                    state.has_moved = false;
-                }
-                EventType::ButtonRelease(Button::Left) => {
-                    let mut clicking_overlay = overlay_click_in_progress.lock().unwrap();
-                    if *clicking_overlay {
-                        *clicking_overlay = false;
-                        let app_clone = app.clone();
-                        let overlay_state_clone = overlay_state.clone();
-                        tauri::async_runtime::spawn(async move {
-                            if let Err(error) = show_or_focus_floating_window(&app_clone, &overlay_state_clone) {
-                                eprintln!("Failed to open floating window: {}", error);
-                            }
-                            let _ = hide_overlay_internal(&app_clone, &overlay_state_clone).await;
-                        });
-                    } else if let Ok(mut is_overlay_visible) = overlay_visible.lock() {
-                        let floating_is_visible = floating_visible.lock().map(|v| *v).unwrap_or(false);
-                        if !*is_overlay_visible && !floating_is_visible {
-                            if state.is_pressed && state.has_moved {
-                                let x = state.last_x;
-                                let y = state.last_y;
-                                let start_x = state.start_x;
-                                show_overlay_at_position(&app, &overlay_state, x, y, start_x);
-                                *is_overlay_visible = true;
-                            }
-                        }
-                    }
-                    
-                    // Reset drag state
-                    state.is_pressed = false;
-                    state.has_moved = false;
-                }
-                EventType::MouseMove { x, y } => {
-                    // Always update the last known mouse position
-                    state.last_x = x;
-                    state.last_y = y;
-                    
-                    if state.is_pressed {
-                        // Check if mouse moved significantly (more than 5 pixels)
-                        let dx = x - state.start_x;
-                        let dy = y - state.start_y;
-                        let distance = (dx * dx + dy * dy).sqrt();
-                        
-                        if distance > 5.0 {
-                            state.has_moved = true;
+            // Key sequences follow every key press, so other keys break them
+            // off. Typing or clicking may move the caret away from the last
+            // replacement, after which undo would select the wrong text.
+            // Modifiers and our own hotkeys don't reach the focused app.
+            match event.event_type {
+                EventType::KeyPress(key) => {
+                    if !modifiers.update(key, true) {
+                        let step = ChordStep { modifiers, key };
+                        if !hotkeys::key_pressed(&app, step) && !hotkeys::is_hotkey(&app, step) {
+                            app.state::<ReplacementHistory>().forget_undo();
                         }
                     }
                 }
-                EventType::KeyPress(_) => {
-                    {
-                        if let Ok(mut clicking) = overlay_click_in_progress.lock() {
-                            *clicking = false;
-                        }
-                    }
-                    let app_clone = app.clone();
-                    let overlay_state_clone = overlay_state.clone();
-                    tauri::async_runtime::spawn(async move {
-                        let _ = hide_overlay_internal(&app_clone, &overlay_state_clone).await;
-                        let _ = hide_floating_window_internal(&app_clone, &overlay_state_clone).await;
-                    });
+                EventType::KeyRelease(key) => {
+                    modifiers.update(key, false);
                 }
+                EventType::ButtonPress(_) => app.state::<ReplacementHistory>().forget_undo(),
                 _ => {}
             }
+
+            if let Some(error) = recorder
+                .as_mut()
+                .and_then(|recorder| recorder.record(&event).err())
+            {
+                eprintln!("Stopped recording input events: {}", error);
+                recorder = None;
+            }
+
+            tracker.set_behavior(overlay_state.behavior());
+            let windows = overlay_state.window_state();
+            for action in tracker.handle(&event.event_type, Instant::now(), &windows) {
+                apply_gesture_action(&app, &overlay_state, action);
+            }
         };
 
         if let Err(error) = listen(callback) {
@@ -549,14 +1168,50 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             greet,
             load_presets_state,
+            migrate_presets_state,
             save_presets_state,
+            import_electron_config,
+            export_presets,
+            import_presets,
+            run_preset,
+            list_provider_models,
+            stream_preset,
+            cancel_completion_stream,
+            hide_result_window,
+            list_replacement_history,
+            undo_last_replacement,
+            set_hotkeys_paused,
+            get_hotkey_status,
             hide_overlay,
             hide_floating_window,
             show_floating_window
         ])
+        .on_window_event(|window, event| {
+            // Focus moved away from the app the last replacement went into.
+            if let WindowEvent::Focused(true) = event {
+                if let Some(history) = window.try_state::<ReplacementHistory>() {
+                    history.forget_undo();
+                }
+            }
+            if window.label() != RESULT_WINDOW_LABEL {
+                return;
+            }
+            // Dismissing the result window abandons whatever it was streaming.
+            if let WindowEvent::CloseRequested { api, .. } = event {
+                api.prevent_close();
+                let _ = window.hide();
+                if let Some(streams) = window.try_state::<CompletionStreams>() {
+                    streams.cancel_all(window.app_handle());
+                }
+            }
+        })
         .setup(|app| {
             let overlay_state = OverlayState::default();
             app.manage(overlay_state.clone());
+            app.manage(CompletionStreams::default());
+            app.manage(PresetRunner::default());
+            app.manage(ReplacementHistory::default());
+            app.manage(HotkeyRegistry::default());
             let app_handle = app.handle();
 
             let data_dir = match app_handle.path().app_data_dir() {
@@ -564,13 +1219,27 @@ pub fn run() {
                 Err(err) => return Err(err.into()),
             };
             fs::create_dir_all(&data_dir)?;
-            let store_path = data_dir.join("gobuddy_presets.json");
-            let initial_state = PresetStateStore::load_from_disk(&store_path)
+            let store_file = JsonFile::new(data_dir.join("gobuddy_presets.json"))
+                .with_migrations(schema::migrate);
+            let vault = SecretVault::open(data_dir.join("gobuddy_secrets.vault"))
                 .map_err(|err| Error::new(ErrorKind::Other, err))?;
-            app.manage(PresetStateStore::new(store_path, initial_state));
+            let preset_store = PresetStateStore::new(store_file, vault);
+            let startup_state = preset_store
+                .load_state()
+                .map_err(|err| Error::new(ErrorKind::Other, err))?
+                .unwrap_or_default();
+            app.manage(preset_store);
+            hotkeys::sync(&app_handle, &startup_state);
+            overlay_state.set_behavior(startup_state.behavior);
 
             ensure_overlay_window(&app_handle);
             ensure_floating_window(&app_handle);
+
+            let preset_app_handle = app_handle.clone();
+            app.listen_any(PRESET_SELECTED_EVENT, move |event| {
+                pipeline::handle_preset_selected(&preset_app_handle, event.payload());
+            });
+
             // Start the global mouse listener
             start_mouse_listener(app_handle.clone(), overlay_state);
             Ok(())
@@ -578,3 +1247,199 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset(model: Option<&str>, temperature: Option<f32>, max_tokens: Option<u32>) -> Preset {
+        Preset {
+            id: "preset-1".into(),
+            name: "Rewrite".into(),
+            prompt: "Rewrite this:".into(),
+            model: model.map(str::to_string),
+            temperature,
+            max_tokens,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn presets_without_generation_fields_still_load() {
+        let state: PersistedState = serde_json::from_str(
+            r#"{
+                "presets": {
+                    "screenshot": [],
+                    "inputField": [{ "id": "preset-1", "name": "Fix", "prompt": "Fix grammar:" }],
+                    "selection": []
+                },
+                "nextPresetId": 2,
+                "activePresetIds": { "screenshot": null, "inputField": "preset-1", "selection": null },
+                "settings": { "autoOpenOnStart": false, "openaiApiKey": "sk-test" },
+                "hotkeys": { "screenshot": "" }
+            }"#,
+        )
+        .unwrap();
+
+        let loaded = state.presets.find("preset-1").unwrap();
+        assert_eq!(loaded.model, None);
+        assert_eq!(loaded.temperature, None);
+        assert_eq!(loaded.max_tokens, None);
+        let serialized = serde_json::to_value(loaded).unwrap();
+        assert!(serialized.get("maxTokens").is_none());
+    }
+
+    /// Fails if deserializing `actual` lost anything `expected` holds.
+    fn assert_kept(expected: &serde_json::Value, actual: &serde_json::Value, path: &str) {
+        use serde_json::Value;
+        match (expected, actual) {
+            (Value::Object(expected), Value::Object(actual)) => {
+                for (key, value) in expected {
+                    let path = format!("{}.{}", path, key);
+                    let actual = actual
+                        .get(key)
+                        .unwrap_or_else(|| panic!("{} was dropped", path));
+                    assert_kept(value, actual, &path);
+                }
+            }
+            (Value::Array(expected), Value::Array(actual)) => {
+                assert_eq!(expected.len(), actual.len(), "{}", path);
+                for (index, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                    assert_kept(expected, actual, &format!("{}[{}]", path, index));
+                }
+            }
+            (Value::Number(expected), Value::Number(actual)) => {
+                assert_eq!(expected.as_f64(), actual.as_f64(), "{}", path)
+            }
+            _ => assert_eq!(expected, actual, "{}", path),
+        }
+    }
+
+    #[test]
+    fn golden_state_files_load_without_losing_data() {
+        for name in schema::tests::GOLDEN_FILES {
+            let (_, expected) = schema::tests::golden(name);
+            let state: PersistedState = serde_json::from_value(expected.clone())
+                .unwrap_or_else(|err| panic!("{}: {}", name, err));
+            assert_eq!(state.schema_version, schema::SCHEMA_VERSION, "{}", name);
+            assert_kept(&expected, &serde_json::to_value(&state).unwrap(), name);
+        }
+    }
+
+    #[test]
+    fn api_keys_stay_out_of_the_state_file_and_the_ui() {
+        let dir = std::env::temp_dir().join(format!("gobuddy-store-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let state_path = dir.join("gobuddy_presets.json");
+        let store = || {
+            PresetStateStore::new(
+                JsonFile::new(state_path.clone()).with_migrations(schema::migrate),
+                SecretVault::with_machine_id(dir.join("gobuddy_secrets.vault"), b"test-machine"),
+            )
+        };
+        let api_key =
+            |state: &PersistedState| state.settings.as_ref().unwrap().openai_api_key.clone();
+
+        // Written by a build that kept the key in plain text, and backed up.
+        let legacy = serde_json::json!({
+            "schemaVersion": 1,
+            "settings": { "autoOpenOnStart": false, "openaiApiKey": "sk-legacy-1234567890" }
+        })
+        .to_string();
+        fs::create_dir_all(dir.join("backups")).unwrap();
+        fs::write(&state_path, &legacy).unwrap();
+        fs::write(dir.join("backups/gobuddy_presets-1.json"), &legacy).unwrap();
+
+        let loaded = store().load_state().unwrap().unwrap();
+        assert_eq!(api_key(&loaded), "sk-legacy-1234567890");
+        let backups = fs::read_dir(dir.join("backups")).unwrap();
+        for file in backups
+            .map(|entry| entry.unwrap().path())
+            .chain([state_path.clone()])
+        {
+            let contents = fs::read_to_string(&file).unwrap();
+            assert!(!contents.contains("sk-legacy"), "{}", file.display());
+        }
+
+        // The UI only sees the mask, and sending it back keeps the key.
+        let masked = loaded.clone().masked();
+        assert!(secrets::is_masked(&api_key(&masked)));
+        assert!(api_key(&masked).ends_with("7890"));
+        let store_in_use = store();
+        let saved = store_in_use.save_state(masked).unwrap();
+        assert_eq!(api_key(&saved), "sk-legacy-1234567890");
+
+        let mut edited = loaded;
+        edited.settings.as_mut().unwrap().openai_api_key = "sk-new-0987654321".into();
+        store_in_use.save_state(edited).unwrap();
+        assert!(!fs::read_to_string(&state_path).unwrap().contains("sk-new"));
+        let reloaded = store().load_state().unwrap().unwrap();
+        assert_eq!(api_key(&reloaded), "sk-new-0987654321");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn preset_overrides_take_precedence_over_settings() {
+        let settings = SettingsState {
+            temperature: Some(0.2),
+            max_tokens: Some(512),
+            ..Default::default()
+        };
+        let config = llm::ProviderConfig {
+            model: "gpt-4o-mini".into(),
+            ..Default::default()
+        };
+
+        let request = build_completion_request(
+            &preset(Some("gpt-4o"), Some(1.1), None),
+            &settings,
+            &config,
+            "text",
+        )
+        .unwrap();
+        assert_eq!(request.model, "gpt-4o");
+        assert_eq!(request.temperature, Some(1.1));
+        assert_eq!(request.max_tokens, Some(512));
+
+        let request =
+            build_completion_request(&preset(None, None, None), &settings, &config, "text")
+                .unwrap();
+        assert_eq!(request.model, "gpt-4o-mini");
+        assert_eq!(request.temperature, Some(0.2));
+    }
+
+    #[test]
+    fn the_first_listed_model_stands_in_for_an_empty_one() {
+        let settings = SettingsState::default();
+        let listed = llm::ProviderConfig {
+            models: vec![" ".into(), "gpt-4.1-mini".into(), "gpt-4o".into()],
+            ..Default::default()
+        };
+        let request =
+            build_completion_request(&preset(None, None, None), &settings, &listed, "").unwrap();
+        assert_eq!(request.model, "gpt-4.1-mini");
+
+        let request = build_completion_request(
+            &preset(None, None, None),
+            &settings,
+            &llm::ProviderConfig::default(),
+            "",
+        )
+        .unwrap();
+        assert_eq!(request.model, settings.provider.default_model());
+    }
+
+    #[test]
+    fn out_of_range_overrides_are_rejected() {
+        let settings = SettingsState::default();
+        let config = llm::ProviderConfig::default();
+
+        assert!(
+            build_completion_request(&preset(None, Some(3.5), None), &settings, &config, "")
+                .is_err()
+        );
+        assert!(
+            build_completion_request(&preset(None, None, Some(0)), &settings, &config, "").is_err()
+        );
+    }
+}
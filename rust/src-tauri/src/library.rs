@@ -0,0 +1,619 @@
+//! Preset libraries: presets exported to a JSON, YAML or TOML file so a team
+//! can share them, for example by keeping them in git, and merged back into
+//! another install.
+//!
+//! A library is grouped by view like `PresetCollection` and carries its own
+//! `version`. Ids only mean something on the machine that wrote the file, so
+//! importing never takes them over: added presets get fresh `preset-N` ids
+//! from `next_preset_id`, and overwritten ones keep the id they had.
+
+use crate::{hotkeys, storage, PersistedState, Preset, PresetCollection, PresetView};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{fs, path::Path};
+
+pub(crate) const LIBRARY_VERSION: u64 = 1;
+
+fn default_library_version() -> u64 {
+    LIBRARY_VERSION
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LibraryFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl LibraryFormat {
+    /// The format named by `path`'s extension.
+    pub(crate) fn from_path(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "json" => Ok(LibraryFormat::Json),
+            "yaml" | "yml" => Ok(LibraryFormat::Yaml),
+            "toml" => Ok(LibraryFormat::Toml),
+            _ => Err(format!(
+                "Can't tell the format of {}; use a .json, .yaml or .toml file",
+                path.display()
+            )),
+        }
+    }
+
+    fn render<T: Serialize>(self, value: &T) -> Result<String, String> {
+        let mut contents = match self {
+            LibraryFormat::Json => {
+                serde_json::to_string_pretty(value).map_err(|err| err.to_string())?
+            }
+            LibraryFormat::Yaml => serde_yaml::to_string(value).map_err(|err| err.to_string())?,
+            LibraryFormat::Toml => toml::to_string_pretty(value).map_err(|err| err.to_string())?,
+        };
+        if !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        Ok(contents)
+    }
+
+    fn parse<T: DeserializeOwned>(self, contents: &str) -> Result<T, String> {
+        match self {
+            LibraryFormat::Json => serde_json::from_str(contents).map_err(|err| err.to_string()),
+            LibraryFormat::Yaml => serde_yaml::from_str(contents).map_err(|err| err.to_string()),
+            LibraryFormat::Toml => toml::from_str(contents).map_err(|err| err.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct PresetLibrary {
+    /// Hand-written libraries may leave it out.
+    #[serde(default = "default_library_version")]
+    pub(crate) version: u64,
+    #[serde(default)]
+    pub(crate) presets: PresetCollection,
+}
+
+/// Read before the presets so a newer file is refused with a clear message
+/// rather than whatever its new shape fails to parse with.
+#[derive(Deserialize)]
+struct LibraryHeader {
+    #[serde(default = "default_library_version")]
+    version: u64,
+}
+
+/// Which existing preset an imported one is the same as.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum MatchBy {
+    /// Re-importing a library exported from this install.
+    #[default]
+    Id,
+    /// Libraries from other machines, whose ids are unrelated to ours.
+    Name,
+}
+
+/// What to do with an imported preset that matches an existing one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Conflict {
+    /// Keep the existing preset.
+    #[default]
+    Skip,
+    /// Replace the existing preset's contents, keeping its id and, unless the
+    /// import brings its own that don't clash, its hotkeys.
+    Overwrite,
+    /// Add the import alongside, renamed if the name is taken.
+    Duplicate,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct ImportOptions {
+    pub(crate) match_by: MatchBy,
+    pub(crate) conflict: Conflict,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ImportReport {
+    /// Files that were read.
+    pub(crate) sources: Vec<String>,
+    pub(crate) imported: Vec<String>,
+    pub(crate) skipped: Vec<SkippedItem>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct SkippedItem {
+    pub(crate) item: String,
+    pub(crate) reason: String,
+}
+
+impl ImportReport {
+    pub(crate) fn skip(&mut self, item: impl Into<String>, reason: impl Into<String>) {
+        self.skipped.push(SkippedItem {
+            item: item.into(),
+            reason: reason.into(),
+        });
+    }
+}
+
+/// A library of the presets in `ids`, or of every preset when `ids` is `None`.
+pub(crate) fn export(
+    presets: &PresetCollection,
+    ids: Option<&[String]>,
+) -> Result<PresetLibrary, String> {
+    let mut exported = PresetCollection::default();
+    for view in PresetView::ALL {
+        *exported.for_view_mut(view) = presets
+            .for_view(view)
+            .iter()
+            .filter(|preset| ids.is_none_or(|ids| ids.contains(&preset.id)))
+            .cloned()
+            .collect();
+    }
+    if let Some(missing) = ids
+        .into_iter()
+        .flatten()
+        .find(|id| presets.find(id).is_none())
+    {
+        return Err(format!("No preset with id {}", missing));
+    }
+    Ok(PresetLibrary {
+        version: LIBRARY_VERSION,
+        presets: exported,
+    })
+}
+
+pub(crate) fn write(
+    path: &Path,
+    format: LibraryFormat,
+    library: &PresetLibrary,
+) -> Result<(), String> {
+    let contents = format.render(library)?;
+    storage::write_atomic(path, contents.as_bytes()).map_err(|err| err.to_string())
+}
+
+/// Reads a library. Presets may leave out their `id`, as hand-written and
+/// third-party libraries often do; importing hands out fresh ids anyway.
+pub(crate) fn read(path: &Path, format: LibraryFormat) -> Result<PresetLibrary, String> {
+    let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let mut document: Value = format.parse(&contents)?;
+    let header: LibraryHeader =
+        serde_json::from_value(document.clone()).map_err(|err| err.to_string())?;
+    if header.version > LIBRARY_VERSION {
+        return Err(format!(
+            "{} was exported by a newer version of GoBuddy (library version {}, this build reads up to {})",
+            path.display(),
+            header.version,
+            LIBRARY_VERSION
+        ));
+    }
+    if let Some(Value::Object(views)) = document.get_mut("presets") {
+        for preset in views
+            .values_mut()
+            .filter_map(Value::as_array_mut)
+            .flatten()
+            .filter_map(Value::as_object_mut)
+        {
+            preset.entry("id").or_insert(Value::from(""));
+        }
+    }
+    serde_json::from_value(document).map_err(|err| err.to_string())
+}
+
+/// Merges `library` into `state`. Presets are matched within their own view;
+/// a preset whose hotkeys would clash with ones already set up is imported
+/// without them, keeping the hotkeys of the preset it replaces.
+pub(crate) fn merge(
+    state: &mut PersistedState,
+    library: PresetLibrary,
+    options: ImportOptions,
+    report: &mut ImportReport,
+) {
+    for view in PresetView::ALL {
+        for preset in library.presets.for_view(view) {
+            merge_preset(state, view, preset.clone(), options, report);
+        }
+    }
+}
+
+fn merge_preset(
+    state: &mut PersistedState,
+    view: PresetView,
+    preset: Preset,
+    options: ImportOptions,
+    report: &mut ImportReport,
+) {
+    let name = preset.name.trim().to_string();
+    let item = format!("{} preset \"{}\"", view.label(), name);
+    if name.is_empty() && preset.prompt.trim().is_empty() {
+        report.skip(item, "it has no name or prompt");
+        return;
+    }
+
+    let errors_before = hotkeys::plan(state).errors.len();
+    let existing =
+        state
+            .presets
+            .for_view(view)
+            .iter()
+            .position(|existing| match options.match_by {
+                MatchBy::Id => !preset.id.is_empty() && existing.id == preset.id,
+                MatchBy::Name => existing.name.trim() == name,
+            });
+    // The hotkeys the preset had before this import, to put back on a clash.
+    let mut previous_hotkeys = (None, None);
+    let (id, item) = match (existing, options.conflict) {
+        (Some(_), Conflict::Skip) => {
+            let key = match options.match_by {
+                MatchBy::Id => "id",
+                MatchBy::Name => "name",
+            };
+            report.skip(item, format!("a preset with the same {} exists", key));
+            return;
+        }
+        (Some(index), Conflict::Overwrite) => {
+            let slot = &mut state.presets.for_view_mut(view)[index];
+            previous_hotkeys = (slot.accelerator.clone(), slot.chord.clone());
+            let accelerator = preset.accelerator.clone().or(slot.accelerator.take());
+            let chord = preset.chord.clone().or(slot.chord.take());
+            *slot = Preset {
+                id: slot.id.clone(),
+                name,
+                accelerator,
+                chord,
+                ..preset
+            };
+            (slot.id.clone(), format!("{} (replaced)", item))
+        }
+        (Some(_), Conflict::Duplicate) => {
+            let name = unused_name(state.presets.for_view(view), &name);
+            let id = add(state, view, Preset { name, ..preset });
+            (id, format!("{} (as a copy)", item))
+        }
+        (None, _) => (add(state, view, Preset { name, ..preset }), item),
+    };
+
+    let plan = hotkeys::plan(state);
+    if plan.errors.len() > errors_before {
+        let reason = plan
+            .errors
+            .last()
+            .map(|error| error.message().to_string())
+            .unwrap_or_default();
+        if let Some(preset) = state
+            .presets
+            .for_view_mut(view)
+            .iter_mut()
+            .find(|preset| preset.id == id)
+        {
+            (preset.accelerator, preset.chord) = previous_hotkeys;
+        }
+        report.skip(format!("Hotkeys of {}", item), reason);
+    }
+    report.imported.push(item);
+}
+
+/// Adds `preset` under a fresh id and returns the id.
+fn add(state: &mut PersistedState, view: PresetView, preset: Preset) -> String {
+    // `next_preset_id` is repaired on load, but the webview saves whatever
+    // counter it has, so make sure the id is really free.
+    let id = loop {
+        let id = format!("preset-{}", state.next_preset_id);
+        state.next_preset_id += 1;
+        if state.presets.find(&id).is_none() {
+            break id;
+        }
+    };
+    let name = if preset.name.is_empty() {
+        id.clone()
+    } else {
+        preset.name.clone()
+    };
+    state.presets.for_view_mut(view).push(Preset {
+        id: id.clone(),
+        name,
+        ..preset
+    });
+    let active = state.active_preset_ids.for_view_mut(view);
+    if active.is_none() {
+        *active = Some(id.clone());
+    }
+    id
+}
+
+/// `name`, or `name (2)`, `name (3)`, ... whichever no preset in `presets`
+/// uses yet.
+fn unused_name(presets: &[Preset], name: &str) -> String {
+    let taken = |candidate: &str| presets.iter().any(|preset| preset.name.trim() == candidate);
+    if !taken(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|number| format!("{} ({})", name, number))
+        .find(|candidate| !taken(candidate))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset(id: &str, name: &str, prompt: &str) -> Preset {
+        Preset {
+            id: id.into(),
+            name: name.into(),
+            prompt: prompt.into(),
+            ..Default::default()
+        }
+    }
+
+    fn state_with(selection: Vec<Preset>, next_preset_id: i32) -> PersistedState {
+        PersistedState {
+            presets: PresetCollection {
+                selection,
+                ..Default::default()
+            },
+            next_preset_id,
+            ..Default::default()
+        }
+    }
+
+    fn names(state: &PersistedState) -> Vec<(&str, &str)> {
+        state
+            .presets
+            .selection
+            .iter()
+            .map(|preset| (preset.id.as_str(), preset.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn libraries_round_trip_in_every_format() {
+        let presets = PresetCollection {
+            selection: vec![
+                Preset {
+                    temperature: Some(0.5),
+                    accelerator: Some("Ctrl + Alt + T".into()),
+                    ..preset("preset-1", "Translate", "Translate to English:")
+                },
+                preset(
+                    "preset-2",
+                    "Summarize",
+                    "Summarize:\n\n- in \"three\" bullets",
+                ),
+            ],
+            input_field: vec![preset("preset-3", "Fix grammar", "Fix the grammar:")],
+            ..Default::default()
+        };
+
+        let dir = std::env::temp_dir().join(format!("gobuddy-library-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["presets.json", "presets.yaml", "presets.toml"] {
+            let path = dir.join(name);
+            let format = LibraryFormat::from_path(&path).unwrap();
+            let ids = ["preset-1".to_string(), "preset-3".to_string()];
+            write(&path, format, &export(&presets, Some(&ids)).unwrap()).unwrap();
+
+            let library = read(&path, format).unwrap();
+            assert_eq!(library.version, LIBRARY_VERSION, "{}", name);
+            let read_back = |presets: &[Preset]| -> Vec<String> {
+                presets
+                    .iter()
+                    .map(|preset| format!("{:?}", preset))
+                    .collect()
+            };
+            assert_eq!(
+                read_back(&library.presets.selection),
+                read_back(&presets.selection[..1]),
+                "{}",
+                name
+            );
+            assert_eq!(
+                read_back(&library.presets.input_field),
+                read_back(&presets.input_field),
+                "{}",
+                name
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(export(&presets, Some(&["preset-9".to_string()])).is_err());
+        assert!(LibraryFormat::from_path(Path::new("presets.txt")).is_err());
+    }
+
+    #[test]
+    fn libraries_without_ids_get_fresh_ones() {
+        let fixtures =
+            std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/library");
+        for name in ["hand_written.yaml", "hand_written.toml"] {
+            let path = fixtures.join(name);
+            let library = read(&path, LibraryFormat::from_path(&path).unwrap())
+                .unwrap_or_else(|err| panic!("{}: {}", name, err));
+            assert_eq!(library.version, LIBRARY_VERSION, "{}", name);
+
+            let mut state = state_with(vec![preset("preset-1", "Explain", "Explain:")], 2);
+            let mut report = ImportReport::default();
+            merge(&mut state, library, ImportOptions::default(), &mut report);
+            assert!(report.skipped.is_empty(), "{}: {:?}", name, report.skipped);
+            assert_eq!(
+                names(&state),
+                [
+                    ("preset-1", "Explain"),
+                    ("preset-3", "Translate"),
+                    ("preset-4", "Summarize")
+                ],
+                "{}",
+                name
+            );
+            assert_eq!(
+                state.presets.selection[2].temperature,
+                Some(0.3),
+                "{}",
+                name
+            );
+            assert_eq!(state.presets.input_field[0].id, "preset-2", "{}", name);
+        }
+    }
+
+    #[test]
+    fn newer_libraries_are_refused() {
+        let path =
+            std::env::temp_dir().join(format!("gobuddy-library-{}-newer.toml", std::process::id()));
+        fs::write(&path, format!("version = {}\n", LIBRARY_VERSION + 1)).unwrap();
+        let error = read(&path, LibraryFormat::Toml).unwrap_err();
+        assert!(error.contains("newer version"), "{}", error);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn conflicts_follow_the_chosen_strategy() {
+        let existing = vec![
+            preset("preset-1", "Translate", "Translate to English:"),
+            preset("preset-2", "Summarize", "Summarize:"),
+        ];
+        // From another machine, where "Summarize" happens to be preset-1.
+        let library = PresetLibrary {
+            version: LIBRARY_VERSION,
+            presets: PresetCollection {
+                selection: vec![
+                    preset("preset-1", "Summarize", "Summarize in one line:"),
+                    preset("preset-7", "Explain", "Explain:"),
+                ],
+                ..Default::default()
+            },
+        };
+        let import = |match_by, conflict| {
+            // A stale counter that would hand out preset-2 again.
+            let mut state = state_with(existing.clone(), 2);
+            let mut report = ImportReport::default();
+            let options = ImportOptions { match_by, conflict };
+            merge(&mut state, library.clone(), options, &mut report);
+            (state, report)
+        };
+
+        let (state, report) = import(MatchBy::Name, Conflict::Skip);
+        assert_eq!(
+            names(&state),
+            [
+                ("preset-1", "Translate"),
+                ("preset-2", "Summarize"),
+                ("preset-3", "Explain")
+            ]
+        );
+        assert_eq!(state.next_preset_id, 4);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.imported, ["Selection preset \"Explain\""]);
+
+        let (state, _) = import(MatchBy::Name, Conflict::Overwrite);
+        assert_eq!(names(&state)[1], ("preset-2", "Summarize"));
+        assert_eq!(state.presets.selection[1].prompt, "Summarize in one line:");
+        assert_eq!(state.presets.selection.len(), 3);
+
+        // Matched by id, the import replaces Translate instead.
+        let (state, _) = import(MatchBy::Id, Conflict::Overwrite);
+        assert_eq!(names(&state)[0], ("preset-1", "Summarize"));
+
+        let (state, report) = import(MatchBy::Name, Conflict::Duplicate);
+        assert_eq!(
+            names(&state),
+            [
+                ("preset-1", "Translate"),
+                ("preset-2", "Summarize"),
+                ("preset-3", "Summarize (2)"),
+                ("preset-4", "Explain")
+            ]
+        );
+        assert_eq!(
+            state.active_preset_ids.selection.as_deref(),
+            Some("preset-3")
+        );
+        assert_eq!(
+            report.imported,
+            [
+                "Selection preset \"Summarize\" (as a copy)",
+                "Selection preset \"Explain\""
+            ]
+        );
+    }
+
+    #[test]
+    fn clashing_hotkeys_are_left_out() {
+        let mut state = state_with(
+            vec![Preset {
+                accelerator: Some("Ctrl + Alt + T".into()),
+                ..preset("preset-1", "Translate", "Translate:")
+            }],
+            2,
+        );
+        let options = ImportOptions {
+            match_by: MatchBy::Name,
+            ..Default::default()
+        };
+        let library = PresetLibrary {
+            version: LIBRARY_VERSION,
+            presets: PresetCollection {
+                selection: vec![Preset {
+                    accelerator: Some("Ctrl + Alt + T".into()),
+                    ..preset("preset-1", "Explain", "Explain:")
+                }],
+                ..Default::default()
+            },
+        };
+        let mut report = ImportReport::default();
+        merge(&mut state, library, options, &mut report);
+        assert_eq!(state.presets.selection[1].accelerator, None);
+        assert_eq!(report.imported, ["Selection preset \"Explain\""]);
+        assert_eq!(
+            report.skipped[0].item,
+            "Hotkeys of Selection preset \"Explain\""
+        );
+        assert!(hotkeys::plan(&state).errors.is_empty());
+    }
+
+    #[test]
+    fn a_replaced_preset_keeps_its_own_hotkeys_when_the_imported_ones_clash() {
+        let mut state = state_with(
+            vec![
+                Preset {
+                    accelerator: Some("Ctrl + Alt + T".into()),
+                    ..preset("preset-1", "Translate", "Translate:")
+                },
+                Preset {
+                    accelerator: Some("Ctrl + Alt + E".into()),
+                    chord: Some("Ctrl+Space, E".into()),
+                    ..preset("preset-2", "Explain", "Explain:")
+                },
+            ],
+            3,
+        );
+        let options = ImportOptions {
+            match_by: MatchBy::Name,
+            conflict: Conflict::Overwrite,
+        };
+        let library = PresetLibrary {
+            version: LIBRARY_VERSION,
+            presets: PresetCollection {
+                selection: vec![Preset {
+                    accelerator: Some("Ctrl + Alt + T".into()),
+                    ..preset("", "Explain", "Explain simply:")
+                }],
+                ..Default::default()
+            },
+        };
+        let mut report = ImportReport::default();
+        merge(&mut state, library, options, &mut report);
+        let explain = &state.presets.selection[1];
+        assert_eq!(explain.prompt, "Explain simply:");
+        assert_eq!(explain.accelerator.as_deref(), Some("Ctrl + Alt + E"));
+        assert_eq!(explain.chord.as_deref(), Some("Ctrl+Space, E"));
+        assert_eq!(
+            report.skipped[0].item,
+            "Hotkeys of Selection preset \"Explain\" (replaced)"
+        );
+        assert!(hotkeys::plan(&state).errors.is_empty());
+    }
+}
@@ -0,0 +1,319 @@
+use super::{
+    http_client, sse::SseDecoder, ChatMessage, CompletionFuture, CompletionRequest, DeltaCallback,
+    LlmProvider, ModelListFuture,
+};
+use serde::{Deserialize, Serialize};
+
+pub(super) const DEFAULT_ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1";
+pub(super) const DEFAULT_ANTHROPIC_MODEL: &str = "claude-3-5-haiku-latest";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+// The Messages API requires an explicit output cap.
+const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+#[derive(Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<&'a ChatMessage>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    #[serde(default)]
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+/// The subset of streaming events GoBuddy acts on; everything else
+/// (`message_start`, `ping`, ...) is skipped.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    ContentBlockDelta {
+        delta: StreamDelta,
+    },
+    MessageStop,
+    Error {
+        error: ApiErrorDetail,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct StreamDelta {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ModelListResponse {
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorResponse {
+    error: ApiErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorDetail {
+    message: String,
+}
+
+/// Client for the Anthropic Messages API.
+pub(crate) struct AnthropicProvider {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl AnthropicProvider {
+    pub(crate) fn new(base_url: &str, api_key: &str) -> Result<Self, String> {
+        Ok(Self {
+            http: http_client()?,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+        })
+    }
+
+    fn authorize(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        builder
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+    }
+
+    async fn send(
+        &self,
+        request: &CompletionRequest,
+        stream: bool,
+    ) -> Result<reqwest::Response, String> {
+        // Anthropic takes system prompts as a top-level field, not a message.
+        let (system, messages): (Vec<&ChatMessage>, Vec<&ChatMessage>) = request
+            .messages
+            .iter()
+            .partition(|message| message.role == "system");
+        let system = if system.is_empty() {
+            None
+        } else {
+            Some(
+                system
+                    .iter()
+                    .map(|message| message.content.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
+            )
+        };
+
+        let body = MessagesRequest {
+            model: &request.model,
+            max_tokens: request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            temperature: request.temperature,
+            system,
+            messages,
+            stream,
+        };
+
+        let response = self
+            .authorize(self.http.post(format!("{}/messages", self.base_url)))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|err| format!("Anthropic request failed: {}", err))?;
+        check_status(response).await
+    }
+
+    async fn complete_inner(&self, request: &CompletionRequest) -> Result<String, String> {
+        let response = self.send(request, false).await?;
+        let parsed: MessagesResponse = response
+            .json()
+            .await
+            .map_err(|err| format!("Unexpected Anthropic response: {}", err))?;
+
+        let output: String = parsed
+            .content
+            .into_iter()
+            .filter(|block| block.kind == "text")
+            .filter_map(|block| block.text)
+            .collect();
+        if output.is_empty() {
+            return Err("Anthropic response contained no output".into());
+        }
+        Ok(output)
+    }
+
+    async fn complete_stream_inner(
+        &self,
+        request: &CompletionRequest,
+        on_delta: DeltaCallback<'_>,
+    ) -> Result<String, String> {
+        let mut response = self.send(request, true).await?;
+        let mut decoder = SseDecoder::default();
+        let mut output = String::new();
+
+        loop {
+            let chunk = response
+                .chunk()
+                .await
+                .map_err(|err| format!("Anthropic stream interrupted: {}", err))?;
+            let payloads = match &chunk {
+                Some(bytes) => decoder.feed(bytes),
+                None => decoder.finish().into_iter().collect(),
+            };
+
+            for payload in payloads {
+                let event: StreamEvent = serde_json::from_str(&payload)
+                    .map_err(|err| format!("Unexpected Anthropic stream payload: {}", err))?;
+                match event {
+                    StreamEvent::ContentBlockDelta { delta } => {
+                        if let Some(text) = delta.text.filter(|text| !text.is_empty()) {
+                            on_delta(&text);
+                            output.push_str(&text);
+                        }
+                    }
+                    StreamEvent::MessageStop => return Ok(output),
+                    StreamEvent::Error { error } => {
+                        return Err(format!("Anthropic stream failed: {}", error.message))
+                    }
+                    StreamEvent::Other => {}
+                }
+            }
+
+            if chunk.is_none() {
+                return Ok(output);
+            }
+        }
+    }
+
+    async fn list_models_inner(&self) -> Result<Vec<String>, String> {
+        let response = self
+            .authorize(self.http.get(format!("{}/models", self.base_url)))
+            .send()
+            .await
+            .map_err(|err| format!("Anthropic request failed: {}", err))?;
+        let parsed: ModelListResponse = check_status(response)
+            .await?
+            .json()
+            .await
+            .map_err(|err| format!("Unexpected Anthropic response: {}", err))?;
+        Ok(parsed.data.into_iter().map(|model| model.id).collect())
+    }
+}
+
+impl LlmProvider for AnthropicProvider {
+    fn complete<'a>(&'a self, request: &'a CompletionRequest) -> CompletionFuture<'a> {
+        Box::pin(self.complete_inner(request))
+    }
+
+    fn complete_stream<'a>(
+        &'a self,
+        request: &'a CompletionRequest,
+        on_delta: DeltaCallback<'a>,
+    ) -> CompletionFuture<'a> {
+        Box::pin(self.complete_stream_inner(request, on_delta))
+    }
+
+    fn list_models(&self) -> ModelListFuture<'_> {
+        Box::pin(self.list_models_inner())
+    }
+}
+
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, String> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    Err(match serde_json::from_str::<ApiErrorResponse>(&body) {
+        Ok(parsed) => format!("Anthropic returned {}: {}", status, parsed.error.message),
+        Err(_) => format!("Anthropic returned {}", status),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::serve_once;
+    use super::*;
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            model: DEFAULT_ANTHROPIC_MODEL.into(),
+            messages: vec![
+                ChatMessage {
+                    role: "system".into(),
+                    content: "Be terse.".into(),
+                },
+                ChatMessage::user("Hello"),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn complete_lifts_system_prompt_and_joins_text_blocks() {
+        let (base_url, server) = serve_once(
+            "200 OK",
+            "application/json",
+            r#"{"content":[{"type":"text","text":"Hi"},{"type":"text","text":" there"}]}"#,
+        );
+        let provider = AnthropicProvider::new(&base_url, "test-key").unwrap();
+
+        let output = tauri::async_runtime::block_on(provider.complete(&request())).unwrap();
+        assert_eq!(output, "Hi there");
+
+        let recorded = server.join().unwrap();
+        assert_eq!(recorded.request_line, "POST /messages HTTP/1.1");
+        assert_eq!(recorded.header("x-api-key"), Some("test-key"));
+        assert_eq!(
+            recorded.header("anthropic-version"),
+            Some(ANTHROPIC_VERSION)
+        );
+        let body = recorded.json();
+        assert_eq!(body["system"], "Be terse.");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(body["max_tokens"], DEFAULT_MAX_TOKENS);
+    }
+
+    #[test]
+    fn complete_stream_reads_text_deltas() {
+        let body = concat!(
+            "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{}}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\n\n",
+            "event: ping\ndata: {\"type\":\"ping\"}\n\n",
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\" there\"}}\n\n",
+            "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+        );
+        let (base_url, server) = serve_once("200 OK", "text/event-stream", body);
+        let provider = AnthropicProvider::new(&base_url, "test-key").unwrap();
+
+        let mut deltas = Vec::new();
+        let mut on_delta = |delta: &str| deltas.push(delta.to_string());
+        let output =
+            tauri::async_runtime::block_on(provider.complete_stream(&request(), &mut on_delta))
+                .unwrap();
+
+        assert_eq!(deltas, vec!["Hi", " there"]);
+        assert_eq!(output, "Hi there");
+        assert_eq!(server.join().unwrap().json()["stream"], true);
+    }
+}
@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use std::{future::Future, pin::Pin, time::Duration};
+
+mod anthropic;
+mod ollama;
+mod openai;
+mod sse;
+#[cfg(test)]
+mod test_support;
+
+pub(crate) use anthropic::AnthropicProvider;
+pub(crate) use ollama::OllamaProvider;
+pub(crate) use openai::OpenAiProvider;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// Applied per read rather than to the whole request so long streams survive.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) struct ChatMessage {
+    pub(crate) role: String,
+    pub(crate) content: String,
+}
+
+impl ChatMessage {
+    pub(crate) fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user".into(),
+            content: content.into(),
+        }
+    }
+}
+
+/// Builds the messages sent for a preset, mirroring the Electron build which
+/// placed the preset prompt above the captured text.
+pub(crate) fn build_preset_messages(prompt: &str, input: &str) -> Vec<ChatMessage> {
+    let prompt = prompt.trim();
+    if prompt.is_empty() {
+        return vec![ChatMessage::user(input)];
+    }
+    vec![ChatMessage::user(format!("{}\n\n{}", prompt, input))]
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct CompletionRequest {
+    pub(crate) model: String,
+    pub(crate) messages: Vec<ChatMessage>,
+    /// Left to the backend's default when unset.
+    pub(crate) temperature: Option<f32>,
+    pub(crate) max_tokens: Option<u32>,
+}
+
+pub(crate) type CompletionFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<String, String>> + Send + 'a>>;
+pub(crate) type ModelListFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Vec<String>, String>> + Send + 'a>>;
+pub(crate) type DeltaCallback<'a> = &'a mut (dyn FnMut(&str) + Send);
+
+/// A chat backend GoBuddy can send preset prompts to.
+pub(crate) trait LlmProvider: Send + Sync {
+    fn complete<'a>(&'a self, request: &'a CompletionRequest) -> CompletionFuture<'a>;
+
+    /// Streams the completion, calling `on_delta` for every partial token as
+    /// it arrives. Resolves with the full output once the stream ends.
+    fn complete_stream<'a>(
+        &'a self,
+        request: &'a CompletionRequest,
+        on_delta: DeltaCallback<'a>,
+    ) -> CompletionFuture<'a>;
+
+    /// Models the backend reports as available.
+    fn list_models(&self) -> ModelListFuture<'_>;
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub(crate) enum ProviderKind {
+    #[default]
+    #[serde(rename = "openai")]
+    OpenAi,
+    #[serde(rename = "anthropic")]
+    Anthropic,
+    #[serde(rename = "ollama")]
+    Ollama,
+    #[serde(rename = "openaiCompatible")]
+    OpenAiCompatible,
+}
+
+impl ProviderKind {
+    pub(crate) fn display_name(self) -> &'static str {
+        match self {
+            ProviderKind::OpenAi => "OpenAI",
+            ProviderKind::Anthropic => "Anthropic",
+            ProviderKind::Ollama => "Ollama",
+            ProviderKind::OpenAiCompatible => "OpenAI-compatible server",
+        }
+    }
+
+    pub(crate) fn default_base_url(self) -> &'static str {
+        match self {
+            ProviderKind::OpenAi => openai::DEFAULT_OPENAI_BASE_URL,
+            ProviderKind::Anthropic => anthropic::DEFAULT_ANTHROPIC_BASE_URL,
+            ProviderKind::Ollama => ollama::DEFAULT_OLLAMA_BASE_URL,
+            ProviderKind::OpenAiCompatible => openai::DEFAULT_COMPATIBLE_BASE_URL,
+        }
+    }
+
+    pub(crate) fn default_model(self) -> &'static str {
+        match self {
+            ProviderKind::OpenAi => openai::DEFAULT_OPENAI_MODEL,
+            ProviderKind::Anthropic => anthropic::DEFAULT_ANTHROPIC_MODEL,
+            ProviderKind::Ollama => ollama::DEFAULT_OLLAMA_MODEL,
+            ProviderKind::OpenAiCompatible => openai::DEFAULT_COMPATIBLE_MODEL,
+        }
+    }
+
+    fn requires_api_key(self) -> bool {
+        matches!(self, ProviderKind::OpenAi | ProviderKind::Anthropic)
+    }
+}
+
+/// Credentials, endpoint and models for a single backend. Empty strings fall
+/// back to the provider's defaults.
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProviderConfig {
+    #[serde(default)]
+    pub(crate) api_key: String,
+    #[serde(default)]
+    pub(crate) base_url: String,
+    #[serde(default)]
+    pub(crate) model: String,
+    /// Models to use with this backend, preferred first. The first one
+    /// stands in when `model` is empty.
+    #[serde(default)]
+    pub(crate) models: Vec<String>,
+}
+
+impl ProviderConfig {
+    pub(crate) fn base_url_or_default(&self, kind: ProviderKind) -> &str {
+        match self.base_url.trim() {
+            "" => kind.default_base_url(),
+            custom => custom,
+        }
+    }
+
+    pub(crate) fn model_or_default(&self, kind: ProviderKind) -> &str {
+        match self.model.trim() {
+            "" => self
+                .models
+                .iter()
+                .map(|model| model.trim())
+                .find(|model| !model.is_empty())
+                .unwrap_or(kind.default_model()),
+            custom => custom,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ProviderSettings {
+    #[serde(default)]
+    pub(crate) openai: ProviderConfig,
+    #[serde(default)]
+    pub(crate) anthropic: ProviderConfig,
+    #[serde(default)]
+    pub(crate) ollama: ProviderConfig,
+    #[serde(default)]
+    pub(crate) openai_compatible: ProviderConfig,
+}
+
+impl ProviderSettings {
+    pub(crate) fn get(&self, kind: ProviderKind) -> &ProviderConfig {
+        match kind {
+            ProviderKind::OpenAi => &self.openai,
+            ProviderKind::Anthropic => &self.anthropic,
+            ProviderKind::Ollama => &self.ollama,
+            ProviderKind::OpenAiCompatible => &self.openai_compatible,
+        }
+    }
+}
+
+pub(crate) fn build_provider(
+    kind: ProviderKind,
+    config: &ProviderConfig,
+) -> Result<Box<dyn LlmProvider>, String> {
+    let api_key = config.api_key.trim();
+    if kind.requires_api_key() && api_key.is_empty() {
+        return Err(format!("{} API key not set", kind.display_name()));
+    }
+
+    let base_url = config.base_url_or_default(kind);
+    let provider: Box<dyn LlmProvider> = match kind {
+        ProviderKind::OpenAi | ProviderKind::OpenAiCompatible => {
+            Box::new(OpenAiProvider::new(base_url, api_key)?.with_name(kind.display_name()))
+        }
+        ProviderKind::Anthropic => Box::new(AnthropicProvider::new(base_url, api_key)?),
+        ProviderKind::Ollama => Box::new(OllamaProvider::new(base_url, api_key)?),
+    };
+    Ok(provider)
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT)
+        .build()
+        .map_err(|err| err.to_string())
+}
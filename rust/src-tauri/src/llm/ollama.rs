@@ -0,0 +1,333 @@
+use super::{
+    http_client, ChatMessage, CompletionFuture, CompletionRequest, DeltaCallback, LlmProvider,
+    ModelListFuture,
+};
+use serde::{Deserialize, Serialize};
+
+pub(super) const DEFAULT_OLLAMA_BASE_URL: &str = "http://localhost:11434";
+pub(super) const DEFAULT_OLLAMA_MODEL: &str = "llama3.2";
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    stream: bool,
+    #[serde(skip_serializing_if = "ChatOptions::is_empty")]
+    options: ChatOptions,
+}
+
+#[derive(Serialize)]
+struct ChatOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+}
+
+impl ChatOptions {
+    fn is_empty(&self) -> bool {
+        self.temperature.is_none() && self.num_predict.is_none()
+    }
+}
+
+/// Ollama answers with one of these per line when streaming and a single one
+/// otherwise.
+#[derive(Deserialize)]
+struct ChatResponse {
+    #[serde(default)]
+    message: Option<ResponseMessage>,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    #[serde(default)]
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<TagEntry>,
+}
+
+#[derive(Deserialize)]
+struct TagEntry {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorResponse {
+    error: String,
+}
+
+/// Client for an Ollama server's native chat API. Ollama itself takes no key;
+/// one is only sent for servers behind an authenticating proxy.
+pub(crate) struct OllamaProvider {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl OllamaProvider {
+    pub(crate) fn new(base_url: &str, api_key: &str) -> Result<Self, String> {
+        Ok(Self {
+            http: http_client()?,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+        })
+    }
+
+    fn authorize(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if self.api_key.is_empty() {
+            builder
+        } else {
+            builder.bearer_auth(&self.api_key)
+        }
+    }
+
+    async fn send(
+        &self,
+        request: &CompletionRequest,
+        stream: bool,
+    ) -> Result<reqwest::Response, String> {
+        let body = ChatRequest {
+            model: &request.model,
+            messages: &request.messages,
+            stream,
+            options: ChatOptions {
+                temperature: request.temperature,
+                num_predict: request.max_tokens,
+            },
+        };
+
+        let response = self
+            .authorize(self.http.post(format!("{}/api/chat", self.base_url)))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|err| format!("Ollama request failed: {}", err))?;
+        check_status(response).await
+    }
+
+    async fn complete_inner(&self, request: &CompletionRequest) -> Result<String, String> {
+        let response = self.send(request, false).await?;
+        let parsed: ChatResponse = response
+            .json()
+            .await
+            .map_err(|err| format!("Unexpected Ollama response: {}", err))?;
+        if let Some(error) = parsed.error {
+            return Err(format!("Ollama failed: {}", error));
+        }
+        parsed
+            .message
+            .map(|message| message.content)
+            .ok_or_else(|| "Ollama response contained no output".to_string())
+    }
+
+    async fn complete_stream_inner(
+        &self,
+        request: &CompletionRequest,
+        on_delta: DeltaCallback<'_>,
+    ) -> Result<String, String> {
+        let mut response = self.send(request, true).await?;
+        let mut pending: Vec<u8> = Vec::new();
+        let mut output = String::new();
+
+        loop {
+            let chunk = response
+                .chunk()
+                .await
+                .map_err(|err| format!("Ollama stream interrupted: {}", err))?;
+            let lines: Vec<Vec<u8>> = match &chunk {
+                Some(bytes) => {
+                    pending.extend_from_slice(bytes);
+                    let mut lines = Vec::new();
+                    while let Some(newline) = pending.iter().position(|byte| *byte == b'\n') {
+                        lines.push(pending.drain(..=newline).collect());
+                    }
+                    lines
+                }
+                None => vec![std::mem::take(&mut pending)],
+            };
+
+            for line in lines {
+                let line = String::from_utf8_lossy(&line);
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+
+                let parsed: ChatResponse = serde_json::from_str(line)
+                    .map_err(|err| format!("Unexpected Ollama stream payload: {}", err))?;
+                if let Some(error) = parsed.error {
+                    return Err(format!("Ollama stream failed: {}", error));
+                }
+                if let Some(message) = parsed.message.filter(|message| !message.content.is_empty())
+                {
+                    on_delta(&message.content);
+                    output.push_str(&message.content);
+                }
+                if parsed.done {
+                    return Ok(output);
+                }
+            }
+
+            if chunk.is_none() {
+                return Ok(output);
+            }
+        }
+    }
+
+    async fn list_models_inner(&self) -> Result<Vec<String>, String> {
+        let response = self
+            .authorize(self.http.get(format!("{}/api/tags", self.base_url)))
+            .send()
+            .await
+            .map_err(|err| format!("Ollama request failed: {}", err))?;
+        let parsed: TagsResponse = check_status(response)
+            .await?
+            .json()
+            .await
+            .map_err(|err| format!("Unexpected Ollama response: {}", err))?;
+        Ok(parsed.models.into_iter().map(|model| model.name).collect())
+    }
+}
+
+impl LlmProvider for OllamaProvider {
+    fn complete<'a>(&'a self, request: &'a CompletionRequest) -> CompletionFuture<'a> {
+        Box::pin(self.complete_inner(request))
+    }
+
+    fn complete_stream<'a>(
+        &'a self,
+        request: &'a CompletionRequest,
+        on_delta: DeltaCallback<'a>,
+    ) -> CompletionFuture<'a> {
+        Box::pin(self.complete_stream_inner(request, on_delta))
+    }
+
+    fn list_models(&self) -> ModelListFuture<'_> {
+        Box::pin(self.list_models_inner())
+    }
+}
+
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, String> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    Err(match serde_json::from_str::<ApiErrorResponse>(&body) {
+        Ok(parsed) => format!("Ollama returned {}: {}", status, parsed.error),
+        Err(_) => format!("Ollama returned {}", status),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_support::serve_once;
+    use super::*;
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            model: DEFAULT_OLLAMA_MODEL.into(),
+            messages: vec![ChatMessage::user("Hello")],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn complete_reads_message_content() {
+        let (base_url, server) = serve_once(
+            "200 OK",
+            "application/json",
+            r#"{"model":"llama3.2","message":{"role":"assistant","content":"Hi!"},"done":true}"#,
+        );
+        let provider = OllamaProvider::new(&base_url, "").unwrap();
+
+        let output = tauri::async_runtime::block_on(provider.complete(&request())).unwrap();
+        assert_eq!(output, "Hi!");
+
+        let recorded = server.join().unwrap();
+        assert_eq!(recorded.request_line, "POST /api/chat HTTP/1.1");
+        assert_eq!(recorded.header("authorization"), None);
+        let body = recorded.json();
+        assert_eq!(body["stream"], false);
+        assert!(body.get("options").is_none());
+    }
+
+    #[test]
+    fn complete_stream_reads_ndjson_lines() {
+        let body = concat!(
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"Hi\"},\"done\":false}\n",
+            "{\"message\":{\"role\":\"assistant\",\"content\":\" there\"},\"done\":false}\n",
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true}\n",
+        );
+        let (base_url, server) = serve_once("200 OK", "application/x-ndjson", body);
+        let provider = OllamaProvider::new(&base_url, "").unwrap();
+
+        let mut deltas = Vec::new();
+        let mut on_delta = |delta: &str| deltas.push(delta.to_string());
+        let output =
+            tauri::async_runtime::block_on(provider.complete_stream(&request(), &mut on_delta))
+                .unwrap();
+
+        assert_eq!(deltas, vec!["Hi", " there"]);
+        assert_eq!(output, "Hi there");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn complete_maps_overrides_to_options() {
+        let (base_url, server) = serve_once(
+            "200 OK",
+            "application/json",
+            r#"{"message":{"role":"assistant","content":"ok"},"done":true}"#,
+        );
+        let provider = OllamaProvider::new(&base_url, "").unwrap();
+        let request = CompletionRequest {
+            temperature: Some(0.0),
+            max_tokens: Some(64),
+            ..request()
+        };
+
+        tauri::async_runtime::block_on(provider.complete(&request)).unwrap();
+        let options = &server.join().unwrap().json()["options"];
+        assert_eq!(options["temperature"], 0.0);
+        assert_eq!(options["num_predict"], 64);
+    }
+
+    #[test]
+    fn list_models_reads_tags() {
+        let (base_url, server) = serve_once(
+            "200 OK",
+            "application/json",
+            r#"{"models":[{"name":"llama3.2:latest"},{"name":"qwen2.5:7b"}]}"#,
+        );
+        let provider = OllamaProvider::new(&base_url, "").unwrap();
+
+        let models = tauri::async_runtime::block_on(provider.list_models()).unwrap();
+        assert_eq!(models, vec!["llama3.2:latest", "qwen2.5:7b"]);
+        assert_eq!(
+            server.join().unwrap().request_line,
+            "GET /api/tags HTTP/1.1"
+        );
+    }
+
+    #[test]
+    fn a_configured_key_is_sent_as_a_bearer_token() {
+        let (base_url, server) = serve_once("200 OK", "application/json", r#"{"models":[]}"#);
+        let provider = OllamaProvider::new(&base_url, "proxy-key").unwrap();
+
+        tauri::async_runtime::block_on(provider.list_models()).unwrap();
+        assert_eq!(
+            server.join().unwrap().header("authorization"),
+            Some("Bearer proxy-key")
+        );
+    }
+}
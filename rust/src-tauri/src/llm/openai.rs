@@ -0,0 +1,394 @@
+use super::{
+    http_client, sse::SseDecoder, ChatMessage, CompletionFuture, CompletionRequest, DeltaCallback,
+    LlmProvider, ModelListFuture,
+};
+use serde::{Deserialize, Serialize};
+
+pub(super) const DEFAULT_OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+pub(super) const DEFAULT_OPENAI_MODEL: &str = "gpt-4o-mini";
+// llama.cpp's `llama-server` default; it serves whichever model it was started with.
+pub(super) const DEFAULT_COMPATIBLE_BASE_URL: &str = "http://localhost:8080/v1";
+pub(super) const DEFAULT_COMPATIBLE_MODEL: &str = "default";
+
+#[derive(Serialize)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionResponse {
+    #[serde(default)]
+    choices: Vec<ChatCompletionChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionChoice {
+    message: ChatCompletionMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionChunk {
+    #[serde(default)]
+    choices: Vec<ChatCompletionChunkChoice>,
+    #[serde(default)]
+    error: Option<ApiErrorDetail>,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionChunkChoice {
+    #[serde(default)]
+    delta: ChatCompletionDelta,
+}
+
+#[derive(Deserialize, Default)]
+struct ChatCompletionDelta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ModelListResponse {
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorResponse {
+    error: ApiErrorDetail,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorDetail {
+    message: String,
+}
+
+/// Client for the OpenAI chat-completions API. Also drives OpenAI-compatible
+/// servers such as llama.cpp, vLLM or LM Studio, which may not need a key.
+pub(crate) struct OpenAiProvider {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    /// Names the backend in errors.
+    name: &'static str,
+}
+
+impl OpenAiProvider {
+    pub(crate) fn new(base_url: &str, api_key: &str) -> Result<Self, String> {
+        Ok(Self {
+            http: http_client()?,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            name: "OpenAI",
+        })
+    }
+
+    /// Names the backend in errors, for servers that aren't OpenAI itself.
+    pub(crate) fn with_name(self, name: &'static str) -> Self {
+        Self { name, ..self }
+    }
+
+    fn authorize(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if self.api_key.is_empty() {
+            builder
+        } else {
+            builder.bearer_auth(&self.api_key)
+        }
+    }
+
+    async fn send(
+        &self,
+        request: &CompletionRequest,
+        stream: bool,
+    ) -> Result<reqwest::Response, String> {
+        let body = ChatCompletionRequest {
+            model: &request.model,
+            messages: &request.messages,
+            temperature: request.temperature,
+            max_tokens: request.max_tokens,
+            stream,
+        };
+
+        let response = self
+            .authorize(
+                self.http
+                    .post(format!("{}/chat/completions", self.base_url)),
+            )
+            .json(&body)
+            .send()
+            .await
+            .map_err(|err| format!("{} request failed: {}", self.name, err))?;
+        check_status(self.name, response).await
+    }
+
+    async fn complete_inner(&self, request: &CompletionRequest) -> Result<String, String> {
+        let response = self.send(request, false).await?;
+        let text = response.text().await.map_err(|err| err.to_string())?;
+
+        let parsed: ChatCompletionResponse = serde_json::from_str(&text)
+            .map_err(|err| format!("Unexpected {} response: {}", self.name, err))?;
+        parsed
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .ok_or_else(|| format!("{} response contained no output", self.name))
+    }
+
+    async fn complete_stream_inner(
+        &self,
+        request: &CompletionRequest,
+        on_delta: DeltaCallback<'_>,
+    ) -> Result<String, String> {
+        let mut response = self.send(request, true).await?;
+        let mut decoder = SseDecoder::default();
+        let mut output = String::new();
+
+        loop {
+            let chunk = response
+                .chunk()
+                .await
+                .map_err(|err| format!("{} stream interrupted: {}", self.name, err))?;
+            let payloads = match &chunk {
+                Some(bytes) => decoder.feed(bytes),
+                None => decoder.finish().into_iter().collect(),
+            };
+
+            for payload in payloads {
+                if payload == "[DONE]" {
+                    return Ok(output);
+                }
+                if let Some(delta) = parse_stream_delta(self.name, &payload)? {
+                    on_delta(&delta);
+                    output.push_str(&delta);
+                }
+            }
+
+            if chunk.is_none() {
+                return Ok(output);
+            }
+        }
+    }
+
+    async fn list_models_inner(&self) -> Result<Vec<String>, String> {
+        let response = self
+            .authorize(self.http.get(format!("{}/models", self.base_url)))
+            .send()
+            .await
+            .map_err(|err| format!("{} request failed: {}", self.name, err))?;
+        let parsed: ModelListResponse = check_status(self.name, response)
+            .await?
+            .json()
+            .await
+            .map_err(|err| format!("Unexpected {} response: {}", self.name, err))?;
+        Ok(parsed.data.into_iter().map(|model| model.id).collect())
+    }
+}
+
+impl LlmProvider for OpenAiProvider {
+    fn complete<'a>(&'a self, request: &'a CompletionRequest) -> CompletionFuture<'a> {
+        Box::pin(self.complete_inner(request))
+    }
+
+    fn complete_stream<'a>(
+        &'a self,
+        request: &'a CompletionRequest,
+        on_delta: DeltaCallback<'a>,
+    ) -> CompletionFuture<'a> {
+        Box::pin(self.complete_stream_inner(request, on_delta))
+    }
+
+    fn list_models(&self) -> ModelListFuture<'_> {
+        Box::pin(self.list_models_inner())
+    }
+}
+
+async fn check_status(
+    name: &str,
+    response: reqwest::Response,
+) -> Result<reqwest::Response, String> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    Err(match serde_json::from_str::<ApiErrorResponse>(&body) {
+        Ok(parsed) => format!("{} returned {}: {}", name, status, parsed.error.message),
+        Err(_) => format!("{} returned {}", name, status),
+    })
+}
+
+fn parse_stream_delta(name: &str, payload: &str) -> Result<Option<String>, String> {
+    let chunk: ChatCompletionChunk = serde_json::from_str(payload)
+        .map_err(|err| format!("Unexpected {} stream payload: {}", name, err))?;
+    if let Some(error) = chunk.error {
+        return Err(format!("{} stream failed: {}", name, error.message));
+    }
+    Ok(chunk
+        .choices
+        .into_iter()
+        .next()
+        .and_then(|choice| choice.delta.content)
+        .filter(|content| !content.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{build_preset_messages, test_support::serve_once};
+    use super::*;
+
+    fn request(messages: Vec<ChatMessage>) -> CompletionRequest {
+        CompletionRequest {
+            model: DEFAULT_OPENAI_MODEL.into(),
+            messages,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn complete_returns_first_choice() {
+        let (base_url, server) = serve_once(
+            "200 OK",
+            "application/json",
+            r#"{"choices":[{"message":{"role":"assistant","content":"Bonjour"}}]}"#,
+        );
+        let provider = OpenAiProvider::new(&format!("{}/v1", base_url), "test-key").unwrap();
+        let request = request(build_preset_messages("Translate to French:", "Hello"));
+
+        let output = tauri::async_runtime::block_on(provider.complete(&request)).unwrap();
+        assert_eq!(output, "Bonjour");
+
+        let recorded = server.join().unwrap();
+        assert_eq!(recorded.request_line, "POST /v1/chat/completions HTTP/1.1");
+        assert_eq!(recorded.header("authorization"), Some("Bearer test-key"));
+        let body = recorded.json();
+        assert_eq!(body["model"], DEFAULT_OPENAI_MODEL);
+        assert_eq!(
+            body["messages"][0]["content"],
+            "Translate to French:\n\nHello"
+        );
+        assert!(body.get("temperature").is_none());
+        assert!(body.get("max_tokens").is_none());
+    }
+
+    #[test]
+    fn complete_sends_generation_overrides() {
+        let (base_url, server) = serve_once(
+            "200 OK",
+            "application/json",
+            r#"{"choices":[{"message":{"content":"ok"}}]}"#,
+        );
+        let provider = OpenAiProvider::new(&base_url, "test-key").unwrap();
+        let request = CompletionRequest {
+            model: "gpt-4o".into(),
+            messages: vec![ChatMessage::user("Rewrite")],
+            temperature: Some(0.9),
+            max_tokens: Some(256),
+        };
+
+        tauri::async_runtime::block_on(provider.complete(&request)).unwrap();
+        let body = server.join().unwrap().json();
+        assert_eq!(body["model"], "gpt-4o");
+        assert!((body["temperature"].as_f64().unwrap() - 0.9).abs() < 1e-6);
+        assert_eq!(body["max_tokens"], 256);
+    }
+
+    #[test]
+    fn complete_surfaces_api_errors() {
+        let (base_url, server) = serve_once(
+            "401 Unauthorized",
+            "application/json",
+            r#"{"error":{"message":"Incorrect API key provided"}}"#,
+        );
+        let provider = OpenAiProvider::new(&base_url, "bad-key").unwrap();
+
+        let error = tauri::async_runtime::block_on(
+            provider.complete(&request(vec![ChatMessage::user("Hi")])),
+        )
+        .unwrap_err();
+        server.join().unwrap();
+        assert!(error.contains("401"));
+        assert!(error.contains("Incorrect API key provided"));
+    }
+
+    #[test]
+    fn complete_stream_forwards_deltas() {
+        let body = concat!(
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Bon\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"jour\"}}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let (base_url, server) = serve_once("200 OK", "text/event-stream", body);
+        let provider = OpenAiProvider::new(&base_url, "test-key").unwrap();
+
+        let mut deltas = Vec::new();
+        let mut on_delta = |delta: &str| deltas.push(delta.to_string());
+        let output = tauri::async_runtime::block_on(
+            provider.complete_stream(&request(vec![ChatMessage::user("Hello")]), &mut on_delta),
+        )
+        .unwrap();
+
+        assert_eq!(deltas, vec!["Bon", "jour"]);
+        assert_eq!(output, "Bonjour");
+        assert_eq!(server.join().unwrap().json()["stream"], true);
+    }
+
+    #[test]
+    fn compatible_servers_skip_authorization_without_key() {
+        let (base_url, server) = serve_once(
+            "200 OK",
+            "application/json",
+            r#"{"object":"list","data":[{"id":"qwen2.5-7b-instruct"}]}"#,
+        );
+        let provider = OpenAiProvider::new(&base_url, "").unwrap();
+
+        let models = tauri::async_runtime::block_on(provider.list_models()).unwrap();
+        assert_eq!(models, vec!["qwen2.5-7b-instruct"]);
+
+        let recorded = server.join().unwrap();
+        assert_eq!(recorded.request_line, "GET /models HTTP/1.1");
+        assert_eq!(recorded.header("authorization"), None);
+    }
+
+    #[test]
+    fn compatible_server_errors_name_the_server() {
+        let (base_url, server) = serve_once(
+            "503 Service Unavailable",
+            "application/json",
+            r#"{"error":{"message":"Model is still loading"}}"#,
+        );
+        let provider = OpenAiProvider::new(&base_url, "")
+            .unwrap()
+            .with_name("OpenAI-compatible server");
+
+        let error = tauri::async_runtime::block_on(
+            provider.complete(&request(vec![ChatMessage::user("Hi")])),
+        )
+        .unwrap_err();
+        server.join().unwrap();
+        assert!(
+            error.starts_with("OpenAI-compatible server returned 503"),
+            "{}",
+            error
+        );
+    }
+}
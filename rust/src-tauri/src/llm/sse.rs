@@ -0,0 +1,86 @@
+/// Incremental decoder for `text/event-stream` bodies. Network chunks are fed
+/// in as they arrive and the `data:` payload of every completed event is
+/// returned; partial lines (including split UTF-8 sequences) stay buffered.
+#[derive(Default)]
+pub(crate) struct SseDecoder {
+    buffer: Vec<u8>,
+    data_lines: Vec<String>,
+}
+
+impl SseDecoder {
+    pub(crate) fn feed(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(newline) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if let Some(event) = self.process_line(line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Flushes an event left open when the stream ended without a blank line.
+    pub(crate) fn finish(&mut self) -> Option<String> {
+        if !self.buffer.is_empty() {
+            let line = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).into_owned();
+            self.process_line(line.trim_end_matches('\r'));
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<String> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if let Some(value) = line.strip_prefix("data:") {
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            self.data_lines.push(value.to_string());
+        }
+        // Comments (":") and the `event:`/`id:`/`retry:` fields carry nothing
+        // the providers need; their payloads are self-describing JSON.
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<String> {
+        if self.data_lines.is_empty() {
+            return None;
+        }
+        Some(std::mem::take(&mut self.data_lines).join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_events_split_across_chunks() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.feed(b"data: {\"a\":").is_empty());
+        assert_eq!(
+            decoder.feed(b"1}\r\n\r\ndata: [DONE]\n\n"),
+            vec!["{\"a\":1}", "[DONE]"]
+        );
+    }
+
+    #[test]
+    fn keeps_split_utf8_sequences_intact() {
+        let mut decoder = SseDecoder::default();
+        let bytes = "data: héllo\n\n".as_bytes();
+        assert!(decoder.feed(&bytes[..8]).is_empty());
+        assert_eq!(decoder.feed(&bytes[8..]), vec!["héllo"]);
+    }
+
+    #[test]
+    fn ignores_comments_and_joins_multiline_data() {
+        let mut decoder = SseDecoder::default();
+        let events = decoder.feed(b": keep-alive\nevent: delta\ndata: one\ndata: two\n\n");
+        assert_eq!(events, vec!["one\ntwo"]);
+        assert_eq!(decoder.feed(b"data: tail").len(), 0);
+        assert_eq!(decoder.finish().as_deref(), Some("tail"));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{clipboard::ClipboardSnapshot, simulate, typing};
+use arboard::Clipboard;
+use serde::{Deserialize, Serialize};
+use std::{thread, time::Duration};
+
+const MAX_OUTPUT_DELAY_MS: u64 = 5_000;
+const MAX_TYPING_RATE: u32 = 1_000;
+
+/// How inline output reaches the focused app.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum OutputMode {
+    #[default]
+    Paste,
+    /// Types the output character by character, for apps that ignore
+    /// synthetic paste. See `typing::type_text` for where it works.
+    Type,
+}
+
+/// Output mode for one app, matched case-insensitively against the name
+/// reported by `focus::focused_app`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AppOutputMode {
+    pub(crate) app: String,
+    pub(crate) mode: OutputMode,
+}
+
+/// Timings for writing output back into the focused app. Apps differ in how
+/// quickly they notice a new clipboard and finish reading a paste, so both
+/// waits are user configurable.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct OutputSettings {
+    /// Wait between filling the clipboard and sending the paste shortcut.
+    pub(crate) paste_delay_ms: u64,
+    /// Wait after pasting before the previous clipboard is put back. On X11
+    /// contents are served on request, so restoring too early pastes the old
+    /// clipboard instead.
+    pub(crate) restore_delay_ms: u64,
+    /// Mode used when neither the preset nor an app rule picks one.
+    pub(crate) mode: OutputMode,
+    pub(crate) app_modes: Vec<AppOutputMode>,
+    /// Characters per second in `OutputMode::Type`.
+    pub(crate) typing_rate: u32,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        Self {
+            paste_delay_ms: 150,
+            restore_delay_ms: 300,
+            mode: OutputMode::default(),
+            app_modes: Vec::new(),
+            typing_rate: 60,
+        }
+    }
+}
+
+impl OutputSettings {
+    /// A preset's own mode wins, then a rule for the target app, then the
+    /// default mode.
+    pub(crate) fn resolve_mode(
+        &self,
+        preset_mode: Option<OutputMode>,
+        target_app: Option<&str>,
+    ) -> OutputMode {
+        preset_mode
+            .or_else(|| {
+                let app = target_app?.trim();
+                self.app_modes
+                    .iter()
+                    .find(|rule| rule.app.trim().eq_ignore_ascii_case(app))
+                    .map(|rule| rule.mode)
+            })
+            .unwrap_or(self.mode)
+    }
+
+    fn paste_delay(&self) -> Duration {
+        Duration::from_millis(self.paste_delay_ms.min(MAX_OUTPUT_DELAY_MS))
+    }
+
+    fn restore_delay(&self) -> Duration {
+        Duration::from_millis(self.restore_delay_ms.min(MAX_OUTPUT_DELAY_MS))
+    }
+
+    fn typing_rate(&self) -> u32 {
+        self.typing_rate.clamp(1, MAX_TYPING_RATE)
+    }
+}
+
+/// Replaces the focused app's selection with `text`. Pasting puts back
+/// whatever the user had on the clipboard afterwards; typing leaves it alone.
+pub(crate) fn replace_selection(
+    text: &str,
+    mode: OutputMode,
+    settings: &OutputSettings,
+) -> Result<(), String> {
+    let _simulating = simulate::Simulating::start();
+    if mode == OutputMode::Type {
+        return typing::type_text(text, settings.typing_rate());
+    }
+
+    let mut clipboard = Clipboard::new().map_err(|err| err.to_string())?;
+    let snapshot = ClipboardSnapshot::capture(&mut clipboard);
+    let written = clipboard
+        .set_text(text.to_string())
+        .map_err(|err| err.to_string())
+        .and_then(|_| {
+            thread::sleep(settings.paste_delay());
+            simulate::paste()
+        });
+    thread::sleep(settings.restore_delay());
+
+    if let Err(error) = snapshot.restore(&mut clipboard) {
+        eprintln!("{}", error);
+    }
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_delays_use_defaults_and_large_ones_are_capped() {
+        let settings: OutputSettings = serde_json::from_str(r#"{"restoreDelayMs":60000}"#).unwrap();
+        assert_eq!(
+            settings.paste_delay_ms,
+            OutputSettings::default().paste_delay_ms
+        );
+        assert_eq!(
+            settings.restore_delay(),
+            Duration::from_millis(MAX_OUTPUT_DELAY_MS)
+        );
+    }
+
+    #[test]
+    fn preset_mode_wins_over_app_rules() {
+        let settings: OutputSettings =
+            serde_json::from_str(r#"{"appModes":[{"app":"Remmina","mode":"type"}]}"#).unwrap();
+
+        assert_eq!(
+            settings.resolve_mode(None, Some("remmina")),
+            OutputMode::Type
+        );
+        assert_eq!(
+            settings.resolve_mode(None, Some("Firefox")),
+            OutputMode::Paste
+        );
+        assert_eq!(settings.resolve_mode(None, None), OutputMode::Paste);
+        assert_eq!(
+            settings.resolve_mode(Some(OutputMode::Paste), Some("Remmina")),
+            OutputMode::Paste
+        );
+    }
+}
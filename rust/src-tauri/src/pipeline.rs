@@ -0,0 +1,158 @@
+use crate::{
+    focus, hide_floating_window_internal, hide_overlay_internal, history::ReplacementHistory,
+    output, prepare_completion, selection, show_result_window, CompletionStreams, OverlayState,
+    PersistedState, Preset, PresetStateStore, PresetView,
+};
+use serde::Deserialize;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
+};
+use tauri::{AppHandle, Manager};
+
+// Time for focus to return to the app the user was working in once the
+// quick panel hides, so the copy shortcut lands there.
+const FOCUS_RETURN_DELAY: Duration = Duration::from_millis(150);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PresetSelectedPayload {
+    view: PresetView,
+    #[serde(default)]
+    preset_id: Option<String>,
+}
+
+/// Lets only one preset run at a time, like the Electron hotkey guards.
+#[derive(Default)]
+pub(crate) struct PresetRunner {
+    busy: AtomicBool,
+}
+
+impl PresetRunner {
+    /// Claims the runner until the returned guard is dropped.
+    pub(crate) fn try_acquire(&self) -> Option<RunnerGuard<'_>> {
+        if self.busy.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+        Some(RunnerGuard(&self.busy))
+    }
+}
+
+pub(crate) struct RunnerGuard<'a>(&'a AtomicBool);
+
+impl Drop for RunnerGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Handles `PRESET_SELECTED_EVENT` from the quick panel.
+pub(crate) fn handle_preset_selected(app: &AppHandle, payload: &str) {
+    match serde_json::from_str::<PresetSelectedPayload>(payload) {
+        Ok(selected) => run_preset_for_view(app, selected.view, selected.preset_id),
+        Err(error) => eprintln!("Ignoring malformed preset selection: {}", error),
+    }
+}
+
+/// Captures the current selection, runs the preset on it and hands the result
+/// to the view's output stage. Without an id the view's active preset is used.
+pub(crate) fn run_preset_for_view(app: &AppHandle, view: PresetView, preset_id: Option<String>) {
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(error) = run(&app_handle, view, preset_id.as_deref()).await {
+            eprintln!("Failed to run preset: {}", error);
+        }
+    });
+}
+
+async fn run(app: &AppHandle, view: PresetView, preset_id: Option<&str>) -> Result<(), String> {
+    let runner = app.state::<PresetRunner>();
+    let _guard = runner
+        .try_acquire()
+        .ok_or("Another preset is still running")?;
+    run_exclusive(app, view, preset_id).await
+}
+
+async fn run_exclusive(
+    app: &AppHandle,
+    view: PresetView,
+    preset_id: Option<&str>,
+) -> Result<(), String> {
+    let state = app
+        .state::<PresetStateStore>()
+        .load_state()?
+        .unwrap_or_default();
+    let preset = resolve_preset(&state, view, preset_id)?;
+    if view == PresetView::Screenshot {
+        return Err("Screenshot presets are not supported yet".into());
+    }
+    let settings = state.settings.unwrap_or_default();
+    // PRIMARY keeps the last highlight long after it is gone, so it is only
+    // trusted for the selection the bubble was just shown for.
+    let capture = selection::CaptureOptions {
+        primary: view == PresetView::Selection
+            && app
+                .try_state::<OverlayState>()
+                .is_some_and(|overlay_state| overlay_state.take_selection_gesture()),
+        ..Default::default()
+    };
+
+    if let Some(overlay_state) = app.try_state::<OverlayState>() {
+        let _ = hide_floating_window_internal(app, &overlay_state).await;
+        let _ = hide_overlay_internal(app, &overlay_state).await;
+    }
+
+    let (input, target_app) = tauri::async_runtime::spawn_blocking(move || {
+        thread::sleep(FOCUS_RETURN_DELAY);
+        let input = selection::capture_selection(capture)?;
+        Ok::<_, String>((input, focus::focused_app()))
+    })
+    .await
+    .map_err(|err| err.to_string())??;
+
+    let (provider, request) = prepare_completion(&preset, &settings, &input)?;
+    match view {
+        PresetView::Selection => {
+            show_result_window(app)?;
+            app.state::<CompletionStreams>()
+                .start(app, preset.id, provider, request);
+        }
+        PresetView::InputField => {
+            let output = provider.complete(&request).await?;
+            let output_settings = settings.output;
+            let mode = output_settings.resolve_mode(preset.output_mode, target_app.as_deref());
+            let replacement = output.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                output::replace_selection(&replacement, mode, &output_settings)
+            })
+            .await
+            .map_err(|err| err.to_string())??;
+            app.state::<ReplacementHistory>()
+                .record(input, output, target_app, mode);
+        }
+        PresetView::Screenshot => {}
+    }
+    Ok(())
+}
+
+fn resolve_preset(
+    state: &PersistedState,
+    view: PresetView,
+    preset_id: Option<&str>,
+) -> Result<Preset, String> {
+    let presets = state.presets.for_view(view);
+    let preset = match preset_id {
+        Some(id) => presets.iter().find(|preset| preset.id == id),
+        None => state
+            .active_preset_ids
+            .for_view(view)
+            .and_then(|id| presets.iter().find(|preset| preset.id == id))
+            .or_else(|| presets.first()),
+    };
+
+    preset.cloned().ok_or_else(|| match preset_id {
+        Some(id) => format!("Preset \"{}\" not found", id),
+        None => "No presets are configured for this view".to_string(),
+    })
+}
@@ -0,0 +1,189 @@
+//! Places the overlay bubble and floating panel on the monitor under the
+//! cursor.
+//!
+//! Three coordinate spaces meet here. rdev reports the cursor in the
+//! desktop's native units: logical points on macOS, physical pixels
+//! elsewhere. Tauri describes monitors and moves windows in physical
+//! pixels. `BehaviorSettings` sizes and offsets are logical pixels, so the
+//! bubble looks the same at 100% and 150% scaling. Layout happens in logical
+//! pixels relative to the monitor's top-left corner and is converted at the
+//! edges.
+
+/// Whether rdev reports the cursor in logical points rather than physical
+/// pixels.
+const CURSOR_IS_LOGICAL: bool = cfg!(target_os = "macos");
+
+/// `(x, y, width, height)`.
+pub(crate) type Rect = (f64, f64, f64, f64);
+
+/// A monitor as Tauri describes it, in physical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Screen {
+    pub(crate) bounds: Rect,
+    /// The bounds minus the taskbar, dock or menu bar.
+    pub(crate) work_area: Rect,
+    pub(crate) scale_factor: f64,
+}
+
+/// Where a window goes: physical values for Tauri, and the same rectangle in
+/// cursor coordinates for hit-testing against later rdev events.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Placement {
+    pub(crate) position: (i32, i32),
+    pub(crate) size: (u32, u32),
+    pub(crate) cursor_bounds: Rect,
+}
+
+impl Screen {
+    fn scale(&self) -> f64 {
+        if self.scale_factor > 0.0 {
+            self.scale_factor
+        } else {
+            1.0
+        }
+    }
+
+    /// Factor from cursor coordinates to physical pixels on this screen.
+    /// Tauri reports a logical-space monitor's position as its logical
+    /// origin times its own scale factor, so the same factor maps points
+    /// anywhere on it.
+    fn cursor_to_physical(&self) -> f64 {
+        if CURSOR_IS_LOGICAL {
+            self.scale()
+        } else {
+            1.0
+        }
+    }
+
+    /// The screen's bounds in cursor coordinates.
+    fn cursor_bounds(&self) -> Rect {
+        let factor = self.cursor_to_physical();
+        let (x, y, width, height) = self.bounds;
+        (x / factor, y / factor, width / factor, height / factor)
+    }
+
+    /// `cursor` in logical pixels from the screen's top-left corner.
+    pub(crate) fn local(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let factor = self.cursor_to_physical();
+        let scale = self.scale();
+        (
+            (x * factor - self.bounds.0) / scale,
+            (y * factor - self.bounds.1) / scale,
+        )
+    }
+
+    /// Puts a `size` window (logical pixels) at `origin` (local logical
+    /// pixels), pulled back inside the work area.
+    pub(crate) fn place(&self, (x, y): (f64, f64), (width, height): (f64, f64)) -> Placement {
+        let scale = self.scale();
+        let (area_x, area_y, area_width, area_height) = self.work_area;
+        let min_x = (area_x - self.bounds.0) / scale;
+        let min_y = (area_y - self.bounds.1) / scale;
+        let max_x = (min_x + area_width / scale - width).max(min_x);
+        let max_y = (min_y + area_height / scale - height).max(min_y);
+        let physical_x = self.bounds.0 + x.clamp(min_x, max_x) * scale;
+        let physical_y = self.bounds.1 + y.clamp(min_y, max_y) * scale;
+        let physical_width = width * scale;
+        let physical_height = height * scale;
+
+        let factor = self.cursor_to_physical();
+        Placement {
+            position: (physical_x.round() as i32, physical_y.round() as i32),
+            size: (
+                physical_width.round() as u32,
+                physical_height.round() as u32,
+            ),
+            cursor_bounds: (
+                physical_x / factor,
+                physical_y / factor,
+                physical_width / factor,
+                physical_height / factor,
+            ),
+        }
+    }
+}
+
+/// The screen containing `cursor`, else the nearest one; `None` only when
+/// there are no screens.
+pub(crate) fn screen_at(screens: &[Screen], (x, y): (f64, f64)) -> Option<&Screen> {
+    let distance = |screen: &&Screen| {
+        let (left, top, width, height) = screen.cursor_bounds();
+        let dx = (left - x).max(x - (left + width)).max(0.0);
+        let dy = (top - y).max(y - (top + height)).max(0.0);
+        dx * dx + dy * dy
+    };
+    screens
+        .iter()
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 1080p primary at 100% and a 4K monitor at 150% to its right, with a
+    // 40px taskbar along the bottom of each.
+    const PRIMARY: Screen = Screen {
+        bounds: (0.0, 0.0, 1920.0, 1080.0),
+        work_area: (0.0, 0.0, 1920.0, 1040.0),
+        scale_factor: 1.0,
+    };
+    const HIDPI: Screen = Screen {
+        bounds: (1920.0, 0.0, 3840.0, 2160.0),
+        work_area: (1920.0, 0.0, 3840.0, 2100.0),
+        scale_factor: 1.5,
+    };
+
+    /// The point `(x, y)` logical pixels into `screen`, in cursor coordinates.
+    fn cursor_on(screen: &Screen, (x, y): (f64, f64)) -> (f64, f64) {
+        let factor = screen.cursor_to_physical();
+        (
+            (screen.bounds.0 + x * screen.scale_factor) / factor,
+            (screen.bounds.1 + y * screen.scale_factor) / factor,
+        )
+    }
+
+    #[test]
+    fn the_screen_under_the_cursor_is_chosen() {
+        let screens = [PRIMARY, HIDPI];
+        assert_eq!(
+            screen_at(&screens, cursor_on(&PRIMARY, (100.0, 100.0))),
+            Some(&PRIMARY)
+        );
+        assert_eq!(
+            screen_at(&screens, cursor_on(&HIDPI, (100.0, 100.0))),
+            Some(&HIDPI)
+        );
+        // Off every screen, e.g. just past the right edge: the nearest wins.
+        let past_edge = cursor_on(&HIDPI, (2600.0, 100.0));
+        assert_eq!(screen_at(&screens, past_edge), Some(&HIDPI));
+        assert_eq!(screen_at(&[], (0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn placement_scales_with_the_screen() {
+        let local = HIDPI.local(cursor_on(&HIDPI, (400.0, 300.0)));
+        assert_eq!(local, (400.0, 300.0));
+
+        let placement = HIDPI.place(local, (32.0, 32.0));
+        assert_eq!(placement.position, (1920 + 600, 450));
+        assert_eq!(placement.size, (48, 48));
+        let (x, y, width, _) = placement.cursor_bounds;
+        assert_eq!((x, y), cursor_on(&HIDPI, (400.0, 300.0)));
+        assert_eq!(width, 48.0 / HIDPI.cursor_to_physical());
+    }
+
+    #[test]
+    fn placement_stays_inside_the_work_area() {
+        // Past the taskbar of the primary screen.
+        let placement = PRIMARY.place((1900.0, 1030.0), (32.0, 32.0));
+        assert_eq!(placement.position, (1920 - 32, 1040 - 32));
+
+        // Left of the HiDPI screen: clamped to its own left edge rather than
+        // landing on the primary.
+        let placement = HIDPI.place((-40.0, -10.0), (120.0, 180.0));
+        assert_eq!(placement.position, (1920, 0));
+        let placement = HIDPI.place((2600.0, 1390.0), (120.0, 180.0));
+        assert_eq!(placement.position, (1920 + 3840 - 180, 2100 - 270));
+    }
+}
@@ -0,0 +1,305 @@
+//! Records the raw rdev event stream as JSONL so gesture bugs can be captured
+//! once and replayed through `GestureTracker` without a display. Recordings
+//! checked in under `fixtures/gestures` run as regression tests; any other
+//! recording can be replayed with
+//! `GOBUDDY_REPLAY_EVENTS=<file> cargo test replay_recording -- --ignored --nocapture`.
+//!
+//! Recording is off unless `GOBUDDY_RECORD_EVENTS` names a file. It captures
+//! every key press too, so don't type anything private while it runs.
+
+use rdev::{Event, EventType};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+pub(crate) const RECORD_EVENTS_VAR: &str = "GOBUDDY_RECORD_EVENTS";
+
+/// One line of a recording.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct RecordedEvent {
+    /// Milliseconds since the first recorded event.
+    pub(crate) t: u64,
+    pub(crate) event: EventType,
+}
+
+pub(crate) struct EventRecorder {
+    file: File,
+    path: PathBuf,
+    started: Option<SystemTime>,
+}
+
+impl EventRecorder {
+    /// Starts a recording at the path in `GOBUDDY_RECORD_EVENTS`, if set.
+    pub(crate) fn from_env() -> Option<Self> {
+        let path = PathBuf::from(std::env::var_os(RECORD_EVENTS_VAR)?);
+        match Self::create(&path) {
+            Ok(recorder) => {
+                eprintln!("Recording input events to {}", path.display());
+                Some(recorder)
+            }
+            Err(error) => {
+                eprintln!(
+                    "Failed to record input events to {}: {}",
+                    path.display(),
+                    error
+                );
+                None
+            }
+        }
+    }
+
+    /// Starts a new recording, replacing whatever `path` held; timestamps
+    /// only make sense within one session.
+    pub(crate) fn create(path: &Path) -> Result<Self, String> {
+        let file = File::create(path).map_err(|err| err.to_string())?;
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            started: None,
+        })
+    }
+
+    /// Appends `event` as one line. Lines are written whole and unbuffered,
+    /// so killing the app mid-session still leaves a readable recording.
+    pub(crate) fn record(&mut self, event: &Event) -> Result<(), String> {
+        let started = *self.started.get_or_insert(event.time);
+        let t = event
+            .time
+            .duration_since(started)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+        let mut line = serde_json::to_string(&RecordedEvent {
+            t,
+            event: event.event_type,
+        })
+        .map_err(|err| err.to_string())?;
+        line.push('\n');
+        self.file
+            .write_all(line.as_bytes())
+            .map_err(|err| format!("{}: {}", self.path.display(), err))
+    }
+}
+
+/// Parses a recording, skipping blank lines.
+#[cfg(test)]
+pub(crate) fn parse_recording(jsonl: &str) -> Result<Vec<RecordedEvent>, String> {
+    jsonl
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|err| format!("line {}: {}", index + 1, err))
+        })
+        .collect()
+}
+
+/// Runs a recording through a fresh `GestureTracker` and returns each action
+/// with the time of the event that caused it. The windows react to actions
+/// the way the listener's `OverlayState` does, only without the delay of
+/// actually showing and hiding them, and as if on one unbounded screen at
+/// 100% scaling.
+#[cfg(test)]
+pub(crate) fn replay(events: &[RecordedEvent]) -> Vec<(u64, crate::gestures::GestureAction)> {
+    use crate::gestures::{GestureTracker, WindowState};
+
+    let start = std::time::Instant::now();
+    let mut tracker = GestureTracker::default();
+    let mut windows = WindowState::default();
+    let mut actions = Vec::new();
+    for recorded in events {
+        let at = start + std::time::Duration::from_millis(recorded.t);
+        for action in tracker.handle(&recorded.event, at, &windows) {
+            simulate_action(&mut windows, action);
+            actions.push((recorded.t, action));
+        }
+    }
+    actions
+}
+
+#[cfg(test)]
+fn simulate_action(
+    windows: &mut crate::gestures::WindowState,
+    action: crate::gestures::GestureAction,
+) {
+    use crate::gestures::{BehaviorSettings, GestureAction};
+
+    let behavior = BehaviorSettings::default();
+    match action {
+        GestureAction::ShowOverlay { x, y, start_x } => {
+            windows.overlay_visible = true;
+            let (x, y) = behavior.overlay_origin(x, y, start_x);
+            let size = behavior.overlay_size;
+            windows.overlay_bounds = Some((x, y, size, size));
+        }
+        GestureAction::HideOverlay => windows.overlay_visible = false,
+        GestureAction::HideFloating => {
+            windows.floating_visible = false;
+            windows.floating_bounds = None;
+        }
+        GestureAction::OpenPanel => {
+            let (overlay_x, overlay_y, _, _) =
+                windows.overlay_bounds.unwrap_or((200.0, 200.0, 0.0, 0.0));
+            let (x, y) = behavior.panel_origin((overlay_x, overlay_y));
+            windows.floating_visible = true;
+            windows.floating_bounds = Some((x, y, behavior.panel_width, behavior.panel_height));
+            windows.overlay_visible = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gestures::GestureAction;
+
+    fn replay_fixture(name: &str) -> Vec<(u64, GestureAction)> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures/gestures")
+            .join(name);
+        let jsonl = std::fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+        let events =
+            parse_recording(&jsonl).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+        replay(&events)
+    }
+
+    #[test]
+    fn recorded_lines_round_trip() {
+        let dir = std::env::temp_dir().join(format!("gobuddy-recording-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("events.jsonl");
+        let start = SystemTime::now();
+        let mut recorder = EventRecorder::create(&path).unwrap();
+        for (offset, event_type) in [
+            (0, EventType::MouseMove { x: 10.0, y: 20.5 }),
+            (16, EventType::ButtonPress(rdev::Button::Left)),
+            (40, EventType::KeyPress(rdev::Key::KeyA)),
+        ] {
+            recorder
+                .record(&Event {
+                    time: start + std::time::Duration::from_millis(offset),
+                    name: None,
+                    event_type,
+                })
+                .unwrap();
+        }
+
+        let events = parse_recording(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let times: Vec<_> = events.iter().map(|recorded| recorded.t).collect();
+        assert_eq!(times, vec![0, 16, 40]);
+        assert_eq!(events[0].event, EventType::MouseMove { x: 10.0, y: 20.5 });
+        assert_eq!(events[2].event, EventType::KeyPress(rdev::Key::KeyA));
+    }
+
+    #[test]
+    fn a_text_drag_shows_the_overlay() {
+        assert_eq!(
+            replay_fixture("drag_select.jsonl"),
+            vec![(
+                412,
+                GestureAction::ShowOverlay {
+                    x: 642.0,
+                    y: 318.0,
+                    start_x: 410.0
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn a_plain_click_shows_nothing() {
+        assert_eq!(replay_fixture("plain_click.jsonl"), vec![]);
+    }
+
+    #[test]
+    fn a_click_with_hand_jitter_shows_nothing() {
+        assert_eq!(replay_fixture("click_with_jitter.jsonl"), vec![]);
+    }
+
+    #[test]
+    fn the_bubble_opens_the_panel_and_a_click_outside_closes_it() {
+        let actions: Vec<_> = replay_fixture("open_panel.jsonl")
+            .into_iter()
+            .map(|(_, action)| action)
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                GestureAction::ShowOverlay {
+                    x: 300.0,
+                    y: 200.0,
+                    start_x: 120.0
+                },
+                GestureAction::OpenPanel,
+                GestureAction::HideFloating,
+            ]
+        );
+    }
+
+    #[test]
+    fn typing_after_a_selection_dismisses_the_overlay() {
+        let actions: Vec<_> = replay_fixture("drag_then_type.jsonl")
+            .into_iter()
+            .map(|(_, action)| action)
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                GestureAction::ShowOverlay {
+                    x: 200.0,
+                    y: 400.0,
+                    start_x: 500.0
+                },
+                GestureAction::HideOverlay,
+                GestureAction::HideFloating,
+            ]
+        );
+    }
+
+    #[test]
+    fn double_and_triple_clicks_show_the_overlay_once() {
+        let overlay = GestureAction::ShowOverlay {
+            x: 420.0,
+            y: 260.0,
+            start_x: 420.0,
+        };
+        assert_eq!(replay_fixture("double_click.jsonl"), vec![(358, overlay)]);
+        assert_eq!(replay_fixture("triple_click.jsonl"), vec![(358, overlay)]);
+    }
+
+    #[test]
+    fn a_shift_arrow_selection_shows_the_overlay_on_release() {
+        let shown: Vec<_> = replay_fixture("shift_arrow_select.jsonl")
+            .into_iter()
+            .filter(|(_, action)| matches!(action, GestureAction::ShowOverlay { .. }))
+            .collect();
+        assert_eq!(
+            shown,
+            vec![(
+                1520,
+                GestureAction::ShowOverlay {
+                    x: 600.0,
+                    y: 300.0,
+                    start_x: 600.0
+                }
+            )]
+        );
+    }
+
+    /// Prints what a recording does: set `GOBUDDY_REPLAY_EVENTS` to its path.
+    #[test]
+    #[ignore = "replays the recording named by GOBUDDY_REPLAY_EVENTS"]
+    fn replay_recording() {
+        let path =
+            std::env::var("GOBUDDY_REPLAY_EVENTS").expect("GOBUDDY_REPLAY_EVENTS is not set");
+        let jsonl = std::fs::read_to_string(&path).unwrap();
+        for (t, action) in replay(&parse_recording(&jsonl).unwrap()) {
+            println!("{:>8}ms  {:?}", t, action);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Versioning for the preset state file. Each document records the
+//! `schemaVersion` it was written with, and `migrate` upgrades older
+//! documents one version at a time before they are deserialized, so a shape
+//! change never relies on `#[serde(default)]` quietly filling in or dropping
+//! data. Files written before versioning count as version 0.
+//!
+//! To change the shape: bump `SCHEMA_VERSION`, append a step to
+//! `MIGRATIONS`, and add a golden pair under `fixtures/state`.
+
+use serde_json::{Map, Value};
+
+pub(crate) const SCHEMA_VERSION: u64 = 1;
+
+const VERSION_KEY: &str = "schemaVersion";
+
+type Migration = fn(&mut Map<String, Value>);
+
+/// `MIGRATIONS[n]` upgrades a version `n` document to version `n + 1`.
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [unversioned_to_v1];
+
+/// The version `document` was written with.
+pub(crate) fn version_of(document: &Value) -> Result<u64, String> {
+    match document.get(VERSION_KEY) {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| format!("{} must be a whole number, not {}", VERSION_KEY, version)),
+    }
+}
+
+/// Upgrades `document` to `SCHEMA_VERSION`. Documents from a newer version
+/// are refused rather than read with fields this build doesn't know about.
+pub(crate) fn migrate(document: Value) -> Result<Value, String> {
+    let version = version_of(&document)?;
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "saved by a newer version of GoBuddy (schema {}, this build reads up to {})",
+            version, SCHEMA_VERSION
+        ));
+    }
+    let Value::Object(mut document) = document else {
+        return Err("expected a JSON object".into());
+    };
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(&mut document);
+        document.insert(VERSION_KEY.into(), Value::from(from as u64 + 1));
+    }
+    Ok(Value::Object(document))
+}
+
+// Keys that serde fills in when missing but rejects when null.
+const DEFAULTED_KEYS: [&str; 4] = ["presets", "nextPresetId", "activePresetIds", "behavior"];
+
+/// Version 0 covers every file written before versioning. They share one
+/// shape, only growing optional fields over time, but hand edits and the
+/// webview's localStorage copy can leave out fields serde requires, and a
+/// missing or stale `nextPresetId` would hand out ids that already exist.
+fn unversioned_to_v1(document: &mut Map<String, Value>) {
+    document.retain(|key, value| !(value.is_null() && DEFAULTED_KEYS.contains(&key.as_str())));
+
+    let mut highest_id = 0;
+    if let Some(Value::Object(views)) = document.get_mut("presets") {
+        views.retain(|_, presets| !presets.is_null());
+        for preset in views
+            .values_mut()
+            .filter_map(Value::as_array_mut)
+            .flatten()
+            .filter_map(Value::as_object_mut)
+        {
+            let id = preset
+                .get("id")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            if let Some(number) = id
+                .strip_prefix("preset-")
+                .and_then(|number| number.parse::<i64>().ok())
+            {
+                highest_id = highest_id.max(number);
+            }
+            preset.entry("name").or_insert(Value::from(id));
+            preset.entry("prompt").or_insert(Value::from(""));
+        }
+    }
+    let next_id = document
+        .get("nextPresetId")
+        .and_then(Value::as_i64)
+        .filter(|next_id| *next_id > highest_id)
+        .unwrap_or(highest_id + 1);
+    document.insert("nextPresetId".into(), Value::from(next_id));
+
+    if let Some(Value::Object(settings)) = document.get_mut("settings") {
+        settings
+            .entry("autoOpenOnStart")
+            .or_insert(Value::from(false));
+        settings.entry("openaiApiKey").or_insert(Value::from(""));
+    }
+    if let Some(Value::Object(hotkeys)) = document.get_mut("hotkeys") {
+        hotkeys.entry("screenshot").or_insert(Value::from(""));
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Every historical format, oldest first. `<name>.json` is a file as that
+    /// build wrote it and `<name>.expected.json` what `migrate` makes of it.
+    pub(crate) const GOLDEN_FILES: [&str; 5] = [
+        "baseline",
+        "providers",
+        "output_modes",
+        "preset_shortcuts",
+        "hand_edited",
+    ];
+
+    pub(crate) fn golden(name: &str) -> (Value, Value) {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures/state");
+        let read = |file: String| -> Value {
+            let path = dir.join(file);
+            let contents = std::fs::read_to_string(&path)
+                .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+            serde_json::from_str(&contents)
+                .unwrap_or_else(|err| panic!("{}: {}", path.display(), err))
+        };
+        (
+            read(format!("{}.json", name)),
+            read(format!("{}.expected.json", name)),
+        )
+    }
+
+    #[test]
+    fn historical_formats_migrate_to_their_golden_files() {
+        for name in GOLDEN_FILES {
+            let (input, expected) = golden(name);
+            assert_eq!(version_of(&input), Ok(0), "{}", name);
+            let migrated = migrate(input).unwrap();
+            assert_eq!(
+                migrated,
+                expected,
+                "{} migrated to\n{}",
+                name,
+                serde_json::to_string_pretty(&migrated).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn current_documents_are_left_alone() {
+        for name in GOLDEN_FILES {
+            let (_, expected) = golden(name);
+            assert_eq!(migrate(expected.clone()), Ok(expected), "{}", name);
+        }
+    }
+
+    #[test]
+    fn newer_or_malformed_versions_are_refused() {
+        let newer = serde_json::json!({ "schemaVersion": SCHEMA_VERSION + 1 });
+        assert!(migrate(newer).unwrap_err().contains("newer version"));
+        assert!(migrate(serde_json::json!({ "schemaVersion": "1" })).is_err());
+        assert!(migrate(serde_json::json!([])).is_err());
+    }
+}
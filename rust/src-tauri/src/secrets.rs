@@ -0,0 +1,357 @@
+//! The secret vault. API keys are kept out of the preset state file, its
+//! backups and everything sent to the webviews, which only ever see a masked
+//! form. They live in a file of their own, encrypted with ChaCha20-Poly1305
+//! under a key derived from this machine's id and a salt that changes on
+//! every save.
+//!
+//! This keeps keys out of synced or copied data folders and out of the
+//! webview. It does not stop code running as the same user on the same
+//! machine, which can derive the same key.
+
+use crate::storage;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+const VAULT_VERSION: u64 = 1;
+// Binds the derived key and every ciphertext to this file format.
+const CONTEXT: &[u8] = b"gobuddy secret vault v1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+// Used when the platform has no machine id to offer.
+const INSTALL_ID_FILE: &str = "gobuddy_install_id";
+
+/// What the UI gets instead of a key. A key long enough to spare them keeps
+/// its last four characters so users can tell which one is saved.
+const MASK: &str = "••••••••";
+const MASK_MIN_LEN: usize = 12;
+
+/// Secrets by name; see `SettingsState::api_keys_mut`.
+pub(crate) type Secrets = BTreeMap<String, String>;
+
+/// The vault file as it is stored; binary fields are base64.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    version: u64,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+pub(crate) struct SecretVault {
+    path: PathBuf,
+    machine_id: Vec<u8>,
+}
+
+impl SecretVault {
+    /// A vault at `path`, keyed to this machine. Without a machine id a random
+    /// one is created once next to the vault.
+    pub(crate) fn open(path: PathBuf) -> Result<Self, String> {
+        let machine_id = match platform::machine_id() {
+            Some(machine_id) => machine_id,
+            None => install_id(&path.with_file_name(INSTALL_ID_FILE))?,
+        };
+        Ok(Self::with_machine_id(path, machine_id.as_bytes()))
+    }
+
+    pub(crate) fn with_machine_id(path: PathBuf, machine_id: &[u8]) -> Self {
+        Self {
+            path,
+            machine_id: machine_id.to_vec(),
+        }
+    }
+
+    /// The stored secrets; none if the vault doesn't exist yet. Fails when the
+    /// file was written on another machine or has been tampered with.
+    pub(crate) fn load(&self) -> Result<Secrets, String> {
+        let contents = match fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Secrets::new()),
+            Err(error) => return Err(format!("{}: {}", self.path.display(), error)),
+        };
+        self.decrypt(&contents)
+            .map_err(|err| format!("{}: {}", self.path.display(), err))
+    }
+
+    /// Replaces the stored secrets with `secrets`.
+    pub(crate) fn save(&self, secrets: &Secrets) -> Result<(), String> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let plaintext = serde_json::to_vec(secrets).map_err(|err| err.to_string())?;
+        let ciphertext = self
+            .cipher(&salt)?
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: CONTEXT,
+                },
+            )
+            .map_err(|_| "Failed to encrypt the secret vault".to_string())?;
+
+        let envelope = Envelope {
+            version: VAULT_VERSION,
+            salt: BASE64.encode(salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        };
+        let serialized = serde_json::to_vec_pretty(&envelope).map_err(|err| err.to_string())?;
+        storage::write_atomic(&self.path, &serialized)?;
+        restrict_to_owner(&self.path)
+    }
+
+    fn decrypt(&self, contents: &[u8]) -> Result<Secrets, String> {
+        let envelope: Envelope = serde_json::from_slice(contents).map_err(|err| err.to_string())?;
+        if envelope.version > VAULT_VERSION {
+            return Err(format!(
+                "written by a newer version of GoBuddy (vault version {}, this build reads up to {})",
+                envelope.version, VAULT_VERSION
+            ));
+        }
+        let decode = |field: &str| BASE64.decode(field).map_err(|err| err.to_string());
+        let salt = decode(&envelope.salt)?;
+        let nonce: [u8; NONCE_LEN] = decode(&envelope.nonce)?
+            .try_into()
+            .map_err(|_| format!("expected a {}-byte nonce", NONCE_LEN))?;
+        let ciphertext = decode(&envelope.ciphertext)?;
+        let plaintext = self
+            .cipher(&salt)?
+            .decrypt(
+                &Nonce::from(nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: CONTEXT,
+                },
+            )
+            .map_err(|_| "can't be decrypted; it was written on another machine or altered")?;
+        serde_json::from_slice(&plaintext).map_err(|err| err.to_string())
+    }
+
+    fn cipher(&self, salt: &[u8]) -> Result<ChaCha20Poly1305, String> {
+        let mut key = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(salt), &self.machine_id)
+            .expand(CONTEXT, &mut key)
+            .map_err(|err| err.to_string())?;
+        Ok(ChaCha20Poly1305::new(&Key::from(key)))
+    }
+}
+
+/// `secret` as the UI may see it.
+pub(crate) fn mask(secret: &str) -> String {
+    if secret.is_empty() || is_masked(secret) {
+        return secret.to_string();
+    }
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() < MASK_MIN_LEN {
+        return MASK.to_string();
+    }
+    let last: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}{}", MASK, last)
+}
+
+/// Whether `value` is a masked secret sent back by the UI unchanged.
+pub(crate) fn is_masked(value: &str) -> bool {
+    value.starts_with(MASK)
+}
+
+/// Empties the strings at `pointers` in `document`. Returns whether any was
+/// set.
+pub(crate) fn scrub<'a>(document: &mut Value, pointers: impl IntoIterator<Item = &'a str>) -> bool {
+    let mut scrubbed = false;
+    for pointer in pointers {
+        if let Some(Value::String(secret)) = document.pointer_mut(pointer) {
+            if !secret.is_empty() {
+                secret.clear();
+                scrubbed = true;
+            }
+        }
+    }
+    scrubbed
+}
+
+/// The random id in `path`, created on first use.
+fn install_id(path: &Path) -> Result<String, String> {
+    if let Ok(id) = fs::read_to_string(path) {
+        if !id.trim().is_empty() {
+            return Ok(id.trim().to_string());
+        }
+    }
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let id: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    storage::write_atomic(path, id.as_bytes())?;
+    restrict_to_owner(path)?;
+    Ok(id)
+}
+
+#[cfg(unix)]
+fn restrict_to_owner(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+        .map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+// The user's profile folder is already private on Windows.
+#[cfg(not(unix))]
+fn restrict_to_owner(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    pub(super) fn machine_id() -> Option<String> {
+        ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+            .iter()
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .map(|id| id.trim().to_string())
+            .find(|id| !id.is_empty())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::process::Command;
+
+    pub(super) fn machine_id() -> Option<String> {
+        let output = Command::new("ioreg")
+            .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| {
+                let (_, value) = line.split_once("\"IOPlatformUUID\" = ")?;
+                Some(value.trim().trim_matches('"').to_string())
+            })
+            .filter(|id| !id.is_empty())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows_sys::Win32::{
+        Foundation::ERROR_SUCCESS,
+        System::Registry::{
+            RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_SZ, RRF_SUBKEY_WOW6464KEY,
+        },
+    };
+
+    pub(super) fn machine_id() -> Option<String> {
+        let subkey: Vec<u16> = "SOFTWARE\\Microsoft\\Cryptography\0"
+            .encode_utf16()
+            .collect();
+        let value: Vec<u16> = "MachineGuid\0".encode_utf16().collect();
+        let mut buffer = [0u16; 64];
+        let mut size = std::mem::size_of_val(&buffer) as u32;
+        let status = unsafe {
+            RegGetValueW(
+                HKEY_LOCAL_MACHINE,
+                subkey.as_ptr(),
+                value.as_ptr(),
+                RRF_RT_REG_SZ | RRF_SUBKEY_WOW6464KEY,
+                std::ptr::null_mut(),
+                buffer.as_mut_ptr().cast(),
+                &mut size,
+            )
+        };
+        if status != ERROR_SUCCESS {
+            return None;
+        }
+        // `size` is in bytes and counts the terminating nul.
+        let len = (size as usize / 2).saturating_sub(1);
+        Some(String::from_utf16_lossy(&buffer[..len])).filter(|id| !id.is_empty())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+mod platform {
+    pub(super) fn machine_id() -> Option<String> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault_path(test: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("gobuddy-secrets-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("gobuddy_secrets.vault")
+    }
+
+    #[test]
+    fn secrets_round_trip_only_on_the_same_machine() {
+        let path = vault_path("round-trip");
+        let vault = SecretVault::with_machine_id(path.clone(), b"machine-a");
+        assert_eq!(vault.load(), Ok(Secrets::new()));
+
+        let secrets = Secrets::from([(
+            "/openaiApiKey".to_string(),
+            "sk-live-1234567890".to_string(),
+        )]);
+        vault.save(&secrets).unwrap();
+        assert_eq!(vault.load(), Ok(secrets));
+        let stored = fs::read_to_string(&path).unwrap();
+        assert!(!stored.contains("sk-live"), "{}", stored);
+
+        let elsewhere = SecretVault::with_machine_id(path.clone(), b"machine-b");
+        assert!(elsewhere.load().unwrap_err().contains("another machine"));
+
+        // Flipping a byte of the ciphertext is caught too.
+        let mut envelope: Envelope = serde_json::from_str(&stored).unwrap();
+        let mut ciphertext = BASE64.decode(&envelope.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        envelope.ciphertext = BASE64.encode(ciphertext);
+        fs::write(&path, serde_json::to_vec(&envelope).unwrap()).unwrap();
+        assert!(vault.load().is_err());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn masks_reveal_at_most_the_last_four_characters() {
+        assert_eq!(mask(""), "");
+        assert_eq!(mask("sk-short"), MASK);
+        let masked = mask("sk-live-1234567890");
+        assert_eq!(masked, format!("{}7890", MASK));
+        assert!(is_masked(&masked));
+        assert_eq!(mask(&masked), masked);
+        assert!(!is_masked("sk-live-1234567890"));
+    }
+
+    #[test]
+    fn scrubbing_empties_only_the_named_secrets() {
+        let mut document = serde_json::json!({
+            "settings": {
+                "openaiApiKey": "sk-old",
+                "providers": { "anthropic": { "apiKey": "", "model": "claude" } }
+            }
+        });
+        let pointers = [
+            "/settings/openaiApiKey",
+            "/settings/providers/anthropic/apiKey",
+        ];
+        assert!(scrub(&mut document, pointers));
+        assert_eq!(document["settings"]["openaiApiKey"], "");
+        assert_eq!(
+            document["settings"]["providers"]["anthropic"]["model"],
+            "claude"
+        );
+        assert!(!scrub(&mut document, pointers));
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{clipboard::ClipboardSnapshot, simulate};
+use arboard::Clipboard;
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+const CLEAR_SETTLE_DELAY: Duration = Duration::from_millis(50);
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct CaptureOptions {
+    /// How long to wait for the copied text to appear.
+    pub(crate) timeout: Duration,
+    pub(crate) poll_interval: Duration,
+    /// Resend the copy shortcut once if nothing arrived after this long, as
+    /// some apps ignore the first synthetic shortcut after a focus change.
+    pub(crate) retry_after: Duration,
+    /// Read the X11 PRIMARY selection instead of copying. Only set right
+    /// after a selection gesture: PRIMARY keeps the last highlight after it
+    /// is gone.
+    pub(crate) primary: bool,
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(1200),
+            poll_interval: Duration::from_millis(60),
+            retry_after: Duration::from_millis(450),
+            primary: false,
+        }
+    }
+}
+
+/// Returns the text selected in the focused app. With `options.primary` on
+/// X11, the PRIMARY selection already holds it, so that is read first;
+/// otherwise the selection is copied through CLIPBOARD, which is snapshotted
+/// beforehand and restored afterwards whether or not anything was captured.
+pub(crate) fn capture_selection(options: CaptureOptions) -> Result<String, String> {
+    let mut clipboard = Clipboard::new().map_err(|err| err.to_string())?;
+
+    // Reading PRIMARY avoids sending Ctrl+C, which terminals treat as SIGINT.
+    #[cfg(target_os = "linux")]
+    if options.primary && !is_wayland_session() {
+        if let Some(text) = read_primary_selection(&mut clipboard) {
+            return Ok(text);
+        }
+    }
+
+    copy_selection(&mut clipboard, options)
+}
+
+/// Under Wayland, PRIMARY as seen through XWayland doesn't follow native
+/// apps' selections.
+#[cfg(target_os = "linux")]
+fn is_wayland_session() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+        || std::env::var("XDG_SESSION_TYPE").is_ok_and(|session| session == "wayland")
+}
+
+#[cfg(target_os = "linux")]
+fn read_primary_selection(clipboard: &mut Clipboard) -> Option<String> {
+    use arboard::{GetExtLinux, LinuxClipboardKind};
+
+    clipboard
+        .get()
+        .clipboard(LinuxClipboardKind::Primary)
+        .text()
+        .ok()
+        .filter(|text| !text.trim().is_empty())
+}
+
+fn copy_selection(clipboard: &mut Clipboard, options: CaptureOptions) -> Result<String, String> {
+    let snapshot = ClipboardSnapshot::capture(clipboard);
+
+    // Clearing first means any text that shows up afterwards is the new copy
+    // rather than whatever the user had before.
+    let _ = clipboard.clear();
+    thread::sleep(CLEAR_SETTLE_DELAY);
+    let captured = simulate::copy()
+        .and_then(|_| wait_for_text(|| clipboard.get_text().ok(), simulate::copy, options));
+
+    if let Err(error) = snapshot.restore(clipboard) {
+        eprintln!("{}", error);
+    }
+    captured
+}
+
+fn wait_for_text<R, C>(
+    mut read: R,
+    mut copy_again: C,
+    options: CaptureOptions,
+) -> Result<String, String>
+where
+    R: FnMut() -> Option<String>,
+    C: FnMut() -> Result<(), String>,
+{
+    let started = Instant::now();
+    let mut retried = false;
+    loop {
+        if let Some(text) = read().filter(|text| !text.trim().is_empty()) {
+            return Ok(text);
+        }
+
+        let elapsed = started.elapsed();
+        if elapsed >= options.timeout {
+            return Err("No selected text was copied".into());
+        }
+        if !retried && elapsed >= options.retry_after {
+            retried = true;
+            copy_again()?;
+        }
+        thread::sleep(options.poll_interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(target_os = "linux")]
+    use arboard::{LinuxClipboardKind, SetExtLinux};
+
+    fn quick() -> CaptureOptions {
+        CaptureOptions {
+            timeout: Duration::from_millis(150),
+            poll_interval: Duration::from_millis(5),
+            retry_after: Duration::from_millis(75),
+            primary: false,
+        }
+    }
+
+    #[test]
+    fn copied_text_is_returned_once_it_arrives() {
+        let mut polls = 0;
+        let mut copies = 0;
+        let captured = wait_for_text(
+            || {
+                polls += 1;
+                (polls > 3).then(|| "selected".to_string())
+            },
+            || {
+                copies += 1;
+                Ok(())
+            },
+            quick(),
+        );
+        assert_eq!(captured, Ok("selected".to_string()));
+        assert_eq!(copies, 0);
+    }
+
+    #[test]
+    fn an_unchanged_clipboard_is_copied_again_once_then_times_out() {
+        let started = Instant::now();
+        let mut copies = 0;
+        let captured = wait_for_text(
+            || None,
+            || {
+                copies += 1;
+                Ok(())
+            },
+            quick(),
+        );
+        assert!(captured.is_err());
+        assert_eq!(copies, 1);
+        assert!(started.elapsed() >= quick().timeout);
+    }
+
+    #[test]
+    fn blank_text_is_not_a_selection() {
+        let captured = wait_for_text(|| Some(" \n".to_string()), || Ok(()), quick());
+        assert!(captured.is_err());
+    }
+
+    #[test]
+    fn a_failed_retry_stops_waiting() {
+        let captured = wait_for_text(|| None, || Err("no focus".to_string()), quick());
+        assert_eq!(captured, Err("no focus".to_string()));
+    }
+
+    // Needs an X server: `xvfb-run cargo test -- --ignored`.
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[ignore = "requires an X11 display"]
+    fn prefers_primary_selection_over_clipboard() {
+        let mut clipboard = Clipboard::new().unwrap();
+        clipboard.set_text("clipboard contents").unwrap();
+        clipboard
+            .set()
+            .clipboard(LinuxClipboardKind::Primary)
+            .text("highlighted text")
+            .unwrap();
+
+        let options = CaptureOptions {
+            primary: true,
+            ..Default::default()
+        };
+        let captured = capture_selection(options).unwrap();
+        assert_eq!(captured, "highlighted text");
+        assert_eq!(clipboard.get_text().unwrap(), "clipboard contents");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[ignore = "requires an X11 display"]
+    fn blank_primary_selection_is_ignored() {
+        let mut clipboard = Clipboard::new().unwrap();
+        clipboard
+            .set()
+            .clipboard(LinuxClipboardKind::Primary)
+            .text("   ")
+            .unwrap();
+
+        assert_eq!(read_primary_selection(&mut clipboard), None);
+    }
+}
@@ -0,0 +1,102 @@
+use rdev::{simulate, EventType, Key};
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::Duration,
+};
+
+// Some platforms drop synthetic events that arrive back to back.
+const EVENT_DELAY: Duration = Duration::from_millis(20);
+
+#[cfg(target_os = "macos")]
+const SHORTCUT_MODIFIER: Key = Key::MetaLeft;
+#[cfg(not(target_os = "macos"))]
+const SHORTCUT_MODIFIER: Key = Key::ControlLeft;
+
+// Guards currently held. The input listener sees GoBuddy's own events as
+// well, and drops key events while this is non-zero.
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+/// Marks synthetic input as in flight for as long as it is held. Guards nest,
+/// so a whole output can hold one around the keystrokes that hold their own.
+pub(crate) struct Simulating(());
+
+impl Simulating {
+    pub(crate) fn start() -> Self {
+        IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+        Self(())
+    }
+}
+
+impl Drop for Simulating {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Whether key events the listener sees right now may be our own.
+pub(crate) fn is_simulating() -> bool {
+    IN_FLIGHT.load(Ordering::SeqCst) > 0
+}
+
+fn send(event_type: &EventType, delay: Duration) -> Result<(), String> {
+    simulate(event_type).map_err(|_| format!("Failed to simulate {:?}", event_type))?;
+    thread::sleep(delay);
+    Ok(())
+}
+
+/// Taps `key` while holding `modifiers`, pausing `delay` after each event.
+/// Modifiers are always released, in reverse order, even if the key fails.
+pub(crate) fn tap_with_modifiers(
+    modifiers: &[Key],
+    key: Key,
+    delay: Duration,
+) -> Result<(), String> {
+    let _simulating = Simulating::start();
+    let mut held = 0;
+    let mut result = Ok(());
+    for modifier in modifiers {
+        result = send(&EventType::KeyPress(*modifier), delay);
+        if result.is_err() {
+            break;
+        }
+        held += 1;
+    }
+    if result.is_ok() {
+        result = send(&EventType::KeyPress(key), delay)
+            .and_then(|_| send(&EventType::KeyRelease(key), delay));
+    }
+    for modifier in modifiers[..held].iter().rev() {
+        let released = send(&EventType::KeyRelease(*modifier), delay);
+        result = result.and(released);
+    }
+    result
+}
+
+/// Taps `key` while holding the platform shortcut modifier (Cmd or Ctrl).
+pub(crate) fn tap_with_shortcut_modifier(key: Key) -> Result<(), String> {
+    tap_with_modifiers(&[SHORTCUT_MODIFIER], key, EVENT_DELAY)
+}
+
+pub(crate) fn copy() -> Result<(), String> {
+    tap_with_shortcut_modifier(Key::KeyC)
+}
+
+pub(crate) fn paste() -> Result<(), String> {
+    tap_with_shortcut_modifier(Key::KeyV)
+}
+
+/// Extends the selection `count` characters to the left of the caret.
+pub(crate) fn select_backwards(count: usize, delay: Duration) -> Result<(), String> {
+    let _simulating = Simulating::start();
+    send(&EventType::KeyPress(Key::ShiftLeft), delay)?;
+    let mut result = Ok(());
+    for _ in 0..count {
+        result = send(&EventType::KeyPress(Key::LeftArrow), delay)
+            .and_then(|_| send(&EventType::KeyRelease(Key::LeftArrow), delay));
+        if result.is_err() {
+            break;
+        }
+    }
+    result.and(send(&EventType::KeyRelease(Key::ShiftLeft), delay))
+}
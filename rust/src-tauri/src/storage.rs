@@ -0,0 +1,525 @@
+//! Crash-safe JSON files. Writes go to a temporary file that is synced and
+//! renamed over the original, so a crash or power loss leaves either the old
+//! or the new contents, never half of each. Before overwriting a file that
+//! still reads back, a timestamped copy is kept in a `backups` directory next
+//! to it, and loading falls back to the newest readable copy when the file
+//! itself is damaged. Damaged files are set aside there too, and rotate out
+//! the same way.
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    fs::{self, File},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const BACKUP_DIR: &str = "backups";
+const BACKUP_EXTENSION: &str = "json";
+const CORRUPT_EXTENSION: &str = "corrupt";
+// Saves happen on nearly every edit, so at most one backup per interval.
+const BACKUP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const BACKUP_LIMIT: usize = 10;
+
+pub(crate) struct JsonFile {
+    path: PathBuf,
+    backup_dir: PathBuf,
+    backup_interval: Duration,
+    backup_limit: usize,
+    migrate: fn(Value) -> Result<Value, String>,
+}
+
+/// Why a file's contents couldn't be read.
+enum ReadError {
+    /// Not JSON, or not the expected shape.
+    Damaged(String),
+    /// Readable, but the migration refused it, e.g. because a newer build
+    /// wrote it. Such files are left exactly as they are.
+    Refused(String),
+}
+
+impl JsonFile {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        let backup_dir = path
+            .parent()
+            .map(|parent| parent.join(BACKUP_DIR))
+            .unwrap_or_else(|| PathBuf::from(BACKUP_DIR));
+        Self {
+            path,
+            backup_dir,
+            backup_interval: BACKUP_INTERVAL,
+            backup_limit: BACKUP_LIMIT,
+            migrate: Ok,
+        }
+    }
+
+    /// Runs `migrate` on every document before it is deserialized, including
+    /// backups. A file it changes is backed up as it was before the upgrade.
+    pub(crate) fn with_migrations(self, migrate: fn(Value) -> Result<Value, String>) -> Self {
+        Self { migrate, ..self }
+    }
+
+    /// Reads the file, or `None` if it doesn't exist yet. A file that no
+    /// longer parses is moved into the backup directory and replaced with the
+    /// newest backup that does; with no usable backup it reads as `None`.
+    pub(crate) fn load<T: DeserializeOwned>(&self) -> Result<Option<T>, String> {
+        let contents = match fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(format!("{}: {}", self.path.display(), error)),
+        };
+        let error = match self.read(&contents) {
+            Ok((value, upgraded)) => {
+                if upgraded {
+                    match self.store_backup(&contents) {
+                        Ok(()) => {
+                            eprintln!("Backed up {} before upgrading it", self.path.display())
+                        }
+                        Err(error) => eprintln!(
+                            "Failed to back up {} before upgrading it: {}",
+                            self.path.display(),
+                            error
+                        ),
+                    }
+                }
+                return Ok(Some(value));
+            }
+            Err(ReadError::Refused(error)) => {
+                return Err(format!("{}: {}", self.path.display(), error))
+            }
+            Err(ReadError::Damaged(error)) => error,
+        };
+
+        eprintln!("{} is damaged: {}", self.path.display(), error);
+        let corrupt = self.set_aside()?;
+        eprintln!("Kept the damaged file as {}", corrupt.display());
+        for backup in self.backups()?.into_iter().rev() {
+            let Ok(contents) = fs::read(&backup) else {
+                continue;
+            };
+            match self.read(&contents) {
+                Ok((value, _)) => {
+                    write_atomic(&self.path, &contents)?;
+                    eprintln!("Restored {} from {}", self.path.display(), backup.display());
+                    return Ok(Some(value));
+                }
+                Err(ReadError::Damaged(error) | ReadError::Refused(error)) => {
+                    eprintln!("Skipping backup {}: {}", backup.display(), error)
+                }
+            }
+        }
+        eprintln!("No readable backup of {}", self.path.display());
+        Ok(None)
+    }
+
+    /// Replaces the file with `value`, first backing up the current contents
+    /// if the last backup is older than the backup interval.
+    pub(crate) fn save<T: Serialize + DeserializeOwned>(&self, value: &T) -> Result<(), String> {
+        if let Err(error) = self.back_up::<T>() {
+            // Never let a failed backup block the save itself.
+            eprintln!("Failed to back up {}: {}", self.path.display(), error);
+        }
+        let serialized = serde_json::to_vec_pretty(value).map_err(|err| err.to_string())?;
+        write_atomic(&self.path, &serialized)
+    }
+
+    /// Runs `rewrite` on every backup that parses and saves the ones it
+    /// reports as changed, e.g. to remove data that must no longer be kept.
+    /// Backups that don't parse and damaged files set aside can't be
+    /// rewritten, so they are deleted.
+    pub(crate) fn rewrite_backups(
+        &self,
+        rewrite: impl Fn(&mut Value) -> bool,
+    ) -> Result<(), String> {
+        for backup in self.backups()? {
+            let contents = fs::read(&backup).map_err(|err| err.to_string())?;
+            let Ok(mut document) = serde_json::from_slice::<Value>(&contents) else {
+                fs::remove_file(&backup).map_err(|err| err.to_string())?;
+                continue;
+            };
+            if rewrite(&mut document) {
+                let serialized =
+                    serde_json::to_vec_pretty(&document).map_err(|err| err.to_string())?;
+                write_atomic(&backup, &serialized)?;
+            }
+        }
+        for corrupt in self.set_aside_files()? {
+            fs::remove_file(&corrupt).map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Parses and migrates `contents`; the flag is set when the migration
+    /// changed anything.
+    fn read<T: DeserializeOwned>(&self, contents: &[u8]) -> Result<(T, bool), ReadError> {
+        let document: Value =
+            serde_json::from_slice(contents).map_err(|err| ReadError::Damaged(err.to_string()))?;
+        let migrated = (self.migrate)(document.clone()).map_err(ReadError::Refused)?;
+        let upgraded = migrated != document;
+        let value =
+            serde_json::from_value(migrated).map_err(|err| ReadError::Damaged(err.to_string()))?;
+        Ok((value, upgraded))
+    }
+
+    /// Backups oldest first.
+    fn backups(&self) -> Result<Vec<PathBuf>, String> {
+        self.stamped_files(BACKUP_EXTENSION)
+    }
+
+    /// Damaged files set aside, oldest first.
+    fn set_aside_files(&self) -> Result<Vec<PathBuf>, String> {
+        self.stamped_files(CORRUPT_EXTENSION)
+    }
+
+    /// Files in the backup directory named after this one with a timestamp
+    /// and `extension`, oldest first.
+    fn stamped_files(&self, extension: &str) -> Result<Vec<PathBuf>, String> {
+        let entries = match fs::read_dir(&self.backup_dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.to_string()),
+        };
+        let mut backups: Vec<(u64, PathBuf)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                let stamp = self.stamp(&name, extension)?;
+                Some((stamp, entry.path()))
+            })
+            .collect();
+        backups.sort();
+        Ok(backups.into_iter().map(|(_, path)| path).collect())
+    }
+
+    /// The timestamp in the name of a stamped file with `extension`, or
+    /// `None` for other files.
+    fn stamp(&self, name: &str, extension: &str) -> Option<u64> {
+        name.strip_prefix(&format!("{}-", self.stem()))?
+            .strip_suffix(extension)?
+            .strip_suffix('.')?
+            .parse()
+            .ok()
+    }
+
+    fn stem(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    fn back_up<T: DeserializeOwned>(&self) -> Result<(), String> {
+        let newest = self.newest_backup_stamp()?;
+        if newest.is_some_and(|newest| {
+            now_millis().saturating_sub(newest) < self.backup_interval.as_millis() as u64
+        }) {
+            return Ok(());
+        }
+
+        let contents = match fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error.to_string()),
+        };
+        // A damaged file would push a good backup out of the rotation.
+        if self.read::<T>(&contents).is_err() {
+            return Ok(());
+        }
+        self.store_backup(&contents)
+    }
+
+    /// A stamp for a new file with `extension`, after every existing one
+    /// even within one millisecond, so names stay unique and ordered.
+    fn next_stamp(&self, extension: &str) -> Result<u64, String> {
+        let now = now_millis();
+        let newest = self
+            .stamped_files(extension)?
+            .last()
+            .and_then(|path| self.stamp(&path.file_name()?.to_string_lossy(), extension));
+        Ok(newest.map_or(now, |newest| now.max(newest + 1)))
+    }
+
+    fn newest_backup_stamp(&self) -> Result<Option<u64>, String> {
+        Ok(self
+            .backups()?
+            .last()
+            .and_then(|path| self.stamp(&path.file_name()?.to_string_lossy(), BACKUP_EXTENSION)))
+    }
+
+    /// Deletes the oldest of `files` past the backup limit.
+    fn prune(&self, files: Vec<PathBuf>) -> Result<(), String> {
+        let excess = files.len().saturating_sub(self.backup_limit);
+        for old in &files[..excess] {
+            fs::remove_file(old).map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Adds `contents` as the newest backup, unless it already is, and drops
+    /// the oldest ones past the limit.
+    fn store_backup(&self, contents: &[u8]) -> Result<(), String> {
+        if let Some(newest) = self.backups()?.last() {
+            if fs::read(newest).is_ok_and(|newest| newest == contents) {
+                return Ok(());
+            }
+        }
+
+        let backup = self.backup_dir.join(format!(
+            "{}-{}.{}",
+            self.stem(),
+            self.next_stamp(BACKUP_EXTENSION)?,
+            BACKUP_EXTENSION
+        ));
+        write_atomic(&backup, contents)?;
+        self.prune(self.backups()?)
+    }
+
+    /// Moves a damaged file into the backup directory, out of the backup
+    /// rotation, and drops the oldest damaged files past the limit.
+    fn set_aside(&self) -> Result<PathBuf, String> {
+        fs::create_dir_all(&self.backup_dir).map_err(|err| err.to_string())?;
+        let corrupt = self.backup_dir.join(format!(
+            "{}-{}.{}",
+            self.stem(),
+            self.next_stamp(CORRUPT_EXTENSION)?,
+            CORRUPT_EXTENSION
+        ));
+        fs::rename(&self.path, &corrupt).map_err(|err| err.to_string())?;
+        self.prune(self.set_aside_files()?)?;
+        Ok(corrupt)
+    }
+}
+
+/// Writes `contents` to a temporary file beside `path`, syncs it, and renames
+/// it over `path`.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty());
+    if let Some(parent) = parent {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp = path.with_file_name(temp_name);
+
+    let write = || -> std::io::Result<()> {
+        let mut file = File::create(&temp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    };
+    if let Err(error) = write() {
+        let _ = fs::remove_file(&temp);
+        return Err(format!("{}: {}", path.display(), error));
+    }
+
+    // Make the rename itself durable. Directories can't be opened this way on
+    // Windows, where the rename is already durable once it returns.
+    #[cfg(unix)]
+    if let Some(parent) = parent {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Library {
+        presets: Vec<String>,
+    }
+
+    fn library(presets: &[&str]) -> Library {
+        Library {
+            presets: presets.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    /// A `JsonFile` in a fresh directory that backs up on every save.
+    fn file(test: &str) -> JsonFile {
+        let dir =
+            std::env::temp_dir().join(format!("gobuddy-storage-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        JsonFile {
+            backup_interval: Duration::ZERO,
+            backup_limit: 3,
+            ..JsonFile::new(dir.join("presets.json"))
+        }
+    }
+
+    fn truncate(path: &Path) {
+        let contents = fs::read(path).unwrap();
+        fs::write(path, &contents[..contents.len() / 2]).unwrap();
+    }
+
+    fn cleanup(file: &JsonFile) {
+        fs::remove_dir_all(file.path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn saves_replace_the_file_without_leaving_a_temporary() {
+        let file = file("atomic");
+        file.save(&library(&["Fix"])).unwrap();
+        file.save(&library(&["Fix", "Shorten"])).unwrap();
+
+        assert_eq!(file.load(), Ok(Some(library(&["Fix", "Shorten"]))));
+        let names: Vec<_> = fs::read_dir(file.path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert!(!names.iter().any(|name| name == "presets.json.tmp"));
+        cleanup(&file);
+    }
+
+    #[test]
+    fn a_write_interrupted_before_the_rename_leaves_the_old_contents() {
+        let file = file("interrupted");
+        file.save(&library(&["Fix"])).unwrap();
+        // What a crash mid-write leaves behind.
+        let temp = file.path.with_file_name("presets.json.tmp");
+        fs::write(&temp, br#"{"presets":["Fi"#).unwrap();
+
+        assert_eq!(file.load(), Ok(Some(library(&["Fix"]))));
+        file.save(&library(&["Shorten"])).unwrap();
+        assert!(!temp.exists());
+        assert_eq!(file.load(), Ok(Some(library(&["Shorten"]))));
+        cleanup(&file);
+    }
+
+    #[test]
+    fn backups_roll_over_and_skip_damaged_files() {
+        let file = file("rolling");
+        assert_eq!(file.load::<Library>(), Ok(None));
+        for count in 1..=5 {
+            let names: Vec<String> = (0..count).map(|n| n.to_string()).collect();
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            file.save(&library(&names)).unwrap();
+        }
+        // Each save backed up the one before it; only the newest three remain.
+        let backups = file.backups().unwrap();
+        assert_eq!(backups.len(), 3);
+        let newest: Library =
+            serde_json::from_slice(&fs::read(backups.last().unwrap()).unwrap()).unwrap();
+        assert_eq!(newest, library(&["0", "1", "2", "3"]));
+
+        truncate(&file.path);
+        file.save(&library(&["replacement"])).unwrap();
+        assert_eq!(file.backups().unwrap(), backups);
+        cleanup(&file);
+    }
+
+    #[test]
+    fn a_truncated_file_falls_back_to_the_newest_readable_backup() {
+        let file = file("fallback");
+        file.save(&library(&["Fix"])).unwrap();
+        file.save(&library(&["Fix", "Shorten"])).unwrap();
+        file.save(&library(&["Fix", "Shorten", "Translate"]))
+            .unwrap();
+        truncate(&file.path);
+        truncate(file.backups().unwrap().last().unwrap());
+
+        assert_eq!(file.load(), Ok(Some(library(&["Fix"]))));
+        // The backup is now the file itself, and the damaged one is kept.
+        assert_eq!(
+            serde_json::from_slice::<Library>(&fs::read(&file.path).unwrap()).unwrap(),
+            library(&["Fix"])
+        );
+        assert_eq!(file.set_aside_files().unwrap().len(), 1);
+        cleanup(&file);
+    }
+
+    #[test]
+    fn damaged_files_set_aside_roll_over() {
+        let file = file("corrupt-rolling");
+        for _ in 0..5 {
+            fs::create_dir_all(file.path.parent().unwrap()).unwrap();
+            fs::write(&file.path, br#"{"presets":["Fi"#).unwrap();
+            assert_eq!(file.load::<Library>(), Ok(None));
+        }
+        assert_eq!(file.set_aside_files().unwrap().len(), 3);
+        cleanup(&file);
+    }
+
+    #[test]
+    fn rewriting_backups_deletes_the_ones_it_cannot_read() {
+        let file = file("rewrite");
+        file.save(&library(&["secret"])).unwrap();
+        file.save(&library(&["secret", "Fix"])).unwrap();
+        file.save(&library(&["Fix"])).unwrap();
+        truncate(file.backups().unwrap().last().unwrap());
+        fs::write(&file.path, br#"{"presets":["secr"#).unwrap();
+        file.load::<Library>().unwrap();
+
+        file.rewrite_backups(|document| {
+            let presets = document["presets"].as_array_mut().unwrap();
+            let before = presets.len();
+            presets.retain(|preset| preset != "secret");
+            presets.len() != before
+        })
+        .unwrap();
+
+        let backups = file.backups().unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(
+            serde_json::from_slice::<Library>(&fs::read(&backups[0]).unwrap()).unwrap(),
+            library(&[])
+        );
+        assert!(file.set_aside_files().unwrap().is_empty());
+        cleanup(&file);
+    }
+
+    #[test]
+    fn upgrades_keep_the_original_and_newer_files_are_left_alone() {
+        let file = file("upgrade").with_migrations(|document| {
+            match document.get("version").and_then(Value::as_u64) {
+                None => {
+                    let mut document = document;
+                    document["version"] = Value::from(1);
+                    Ok(document)
+                }
+                Some(1) => Ok(document),
+                Some(version) => Err(format!("unknown version {}", version)),
+            }
+        });
+        write_atomic(&file.path, br#"{"presets":["Fix"]}"#).unwrap();
+
+        assert_eq!(file.load(), Ok(Some(library(&["Fix"]))));
+        let backups = file.backups().unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(fs::read(&backups[0]).unwrap(), br#"{"presets":["Fix"]}"#);
+        // Loading again before a save doesn't pile up copies.
+        file.load::<Library>().unwrap();
+        assert_eq!(file.backups().unwrap(), backups);
+
+        write_atomic(&file.path, br#"{"version":2,"presets":[]}"#).unwrap();
+        let error = file.load::<Library>().unwrap_err();
+        assert!(error.contains("unknown version 2"), "{}", error);
+        assert!(file.path.exists());
+        cleanup(&file);
+    }
+
+    #[test]
+    fn a_truncated_file_without_backups_reads_as_missing() {
+        let file = file("no-backup");
+        file.save(&library(&["Fix"])).unwrap();
+        truncate(&file.path);
+
+        assert_eq!(file.load::<Library>(), Ok(None));
+        assert!(!file.path.exists());
+        cleanup(&file);
+    }
+}
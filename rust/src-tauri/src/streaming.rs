@@ -0,0 +1,138 @@
+use crate::{
+    llm::{CompletionRequest, LlmProvider},
+    COMPLETION_STREAM_EVENT,
+};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+use tauri::{async_runtime::JoinHandle, AppHandle, Emitter, Manager};
+
+/// Frames emitted on `COMPLETION_STREAM_EVENT`. Every stream opens with
+/// `start`, carries zero or more `delta` frames and closes with exactly one
+/// of `done`, `error` or `cancelled`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub(crate) enum StreamFrame {
+    #[serde(rename_all = "camelCase")]
+    Start { request_id: u64, preset_id: String },
+    #[serde(rename_all = "camelCase")]
+    Delta { request_id: u64, text: String },
+    #[serde(rename_all = "camelCase")]
+    Done { request_id: u64, output: String },
+    #[serde(rename_all = "camelCase")]
+    Error { request_id: u64, message: String },
+    #[serde(rename_all = "camelCase")]
+    Cancelled { request_id: u64 },
+}
+
+fn emit_frame(app: &AppHandle, frame: StreamFrame) {
+    if let Err(error) = app.emit(COMPLETION_STREAM_EVENT, frame) {
+        eprintln!("Failed to emit completion stream frame: {}", error);
+    }
+}
+
+/// Tracks in-flight streaming completions so they can be cancelled when the
+/// result window is dismissed.
+#[derive(Default)]
+pub(crate) struct CompletionStreams {
+    next_request_id: AtomicU64,
+    active: Mutex<HashMap<u64, JoinHandle<()>>>,
+}
+
+impl CompletionStreams {
+    pub(crate) fn start(
+        &self,
+        app: &AppHandle,
+        preset_id: String,
+        provider: Box<dyn LlmProvider>,
+        request: CompletionRequest,
+    ) -> u64 {
+        let request_id = self.next_request_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let app_handle = app.clone();
+
+        // Holding the lock while spawning guarantees the handle is registered
+        // before the task can try to remove itself.
+        let mut active = self.active.lock().unwrap_or_else(|err| err.into_inner());
+        let handle = tauri::async_runtime::spawn(async move {
+            emit_frame(
+                &app_handle,
+                StreamFrame::Start {
+                    request_id,
+                    preset_id,
+                },
+            );
+
+            let mut on_delta = |delta: &str| {
+                emit_frame(
+                    &app_handle,
+                    StreamFrame::Delta {
+                        request_id,
+                        text: delta.to_string(),
+                    },
+                );
+            };
+            let result = provider.complete_stream(&request, &mut on_delta).await;
+
+            let still_active = app_handle
+                .try_state::<CompletionStreams>()
+                .map(|streams| streams.finish(request_id))
+                .unwrap_or(true);
+            if !still_active {
+                return;
+            }
+
+            let frame = match result {
+                Ok(output) => StreamFrame::Done { request_id, output },
+                Err(message) => StreamFrame::Error {
+                    request_id,
+                    message,
+                },
+            };
+            emit_frame(&app_handle, frame);
+        });
+        active.insert(request_id, handle);
+
+        request_id
+    }
+
+    /// Removes a finished stream. Returns false when it was cancelled first.
+    fn finish(&self, request_id: u64) -> bool {
+        self.active
+            .lock()
+            .map(|mut active| active.remove(&request_id).is_some())
+            .unwrap_or(false)
+    }
+
+    pub(crate) fn cancel(&self, app: &AppHandle, request_id: u64) -> bool {
+        let handle = match self.active.lock() {
+            Ok(mut active) => active.remove(&request_id),
+            Err(_) => None,
+        };
+
+        match handle {
+            Some(handle) => {
+                handle.abort();
+                emit_frame(app, StreamFrame::Cancelled { request_id });
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn cancel_all(&self, app: &AppHandle) {
+        let drained: Vec<(u64, JoinHandle<()>)> = match self.active.lock() {
+            Ok(mut active) => active.drain().collect(),
+            Err(_) => return,
+        };
+
+        for (request_id, handle) in drained {
+            handle.abort();
+            emit_frame(app, StreamFrame::Cancelled { request_id });
+        }
+    }
+}
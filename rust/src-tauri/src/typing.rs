@@ -0,0 +1,275 @@
+use crate::simulate;
+use rdev::Key;
+use std::{thread, time::Duration};
+
+// Gap between the press and release events of a single character; the
+// typing rate governs the pause between characters.
+const KEY_EVENT_DELAY: Duration = Duration::from_millis(2);
+
+/// One character's worth of synthetic input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Keystroke {
+    /// Return and Tab, which apps handle as keys rather than text.
+    Key(Key),
+    /// Any other character, sent by its Unicode value.
+    Char(char),
+}
+
+/// Turns text into keystrokes. Line endings of any style become a single
+/// Return.
+pub(crate) fn plan_keystrokes(text: &str) -> Vec<Keystroke> {
+    let mut keystrokes = Vec::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\r' && chars.peek() == Some(&'\n') {
+            continue;
+        }
+        keystrokes.push(match ch {
+            '\n' | '\r' => Keystroke::Key(Key::Return),
+            '\t' => Keystroke::Key(Key::Tab),
+            _ => Keystroke::Char(ch),
+        });
+    }
+    keystrokes
+}
+
+/// Types `text` into the focused app at `chars_per_second`.
+///
+/// Characters go out by their Unicode value rather than as physical keys, so
+/// the target app's keyboard layout doesn't matter: `KEYEVENTF_UNICODE` on
+/// Windows, a key event carrying the string on macOS, and on X11 a spare
+/// keycode remapped to each character in turn. Wayland has no way for one
+/// client to type into another, so there only XWayland apps can be reached.
+pub(crate) fn type_text(text: &str, chars_per_second: u32) -> Result<(), String> {
+    let interval = Duration::from_secs(1) / chars_per_second.max(1);
+    // Covers the pauses between characters too.
+    let _simulating = simulate::Simulating::start();
+    let mut typer = platform::Typer::new()?;
+    for keystroke in plan_keystrokes(text) {
+        match keystroke {
+            Keystroke::Key(key) => simulate::tap_with_modifiers(&[], key, KEY_EVENT_DELAY)?,
+            Keystroke::Char(ch) => typer.type_char(ch)?,
+        }
+        thread::sleep(interval);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::KEY_EVENT_DELAY;
+    use std::thread;
+    use x11rb::{
+        connection::Connection,
+        protocol::{
+            xproto::{ConnectionExt as _, Keycode, Keysym, KEY_PRESS_EVENT, KEY_RELEASE_EVENT},
+            xtest::ConnectionExt as _,
+        },
+        rust_connection::RustConnection,
+        wrapper::ConnectionExt as _,
+        CURRENT_TIME, NONE,
+    };
+
+    const NO_SYMBOL: Keysym = 0;
+
+    /// Borrows a keycode the keyboard doesn't use and maps it to each
+    /// character before pressing it, as xdotool does. Apps read the new
+    /// mapping before the key press that follows it.
+    pub(super) struct Typer {
+        conn: RustConnection,
+        keycode: Keycode,
+        keysyms_per_keycode: u8,
+    }
+
+    impl Typer {
+        pub(super) fn new() -> Result<Self, String> {
+            let (conn, _) = x11rb::connect(None).map_err(|err| err.to_string())?;
+            let (min_keycode, max_keycode) = (conn.setup().min_keycode, conn.setup().max_keycode);
+            let mapping = conn
+                .get_keyboard_mapping(min_keycode, max_keycode - min_keycode + 1)
+                .map_err(|err| err.to_string())?
+                .reply()
+                .map_err(|err| err.to_string())?;
+            let keysyms_per_keycode = mapping.keysyms_per_keycode;
+            let spare = mapping
+                .keysyms
+                .chunks(usize::from(keysyms_per_keycode.max(1)))
+                .rposition(|keysyms| keysyms.iter().all(|keysym| *keysym == NO_SYMBOL))
+                .ok_or("Every keycode is in use, so there is none to type with")?;
+            Ok(Self {
+                keycode: min_keycode + spare as u8,
+                conn,
+                keysyms_per_keycode,
+            })
+        }
+
+        pub(super) fn type_char(&mut self, ch: char) -> Result<(), String> {
+            self.map(keysym(ch))?;
+            for event in [KEY_PRESS_EVENT, KEY_RELEASE_EVENT] {
+                self.conn
+                    .xtest_fake_input(event, self.keycode, CURRENT_TIME, NONE, 0, 0, 0)
+                    .map_err(|err| err.to_string())?;
+                self.conn.sync().map_err(|err| err.to_string())?;
+                thread::sleep(KEY_EVENT_DELAY);
+            }
+            Ok(())
+        }
+
+        fn map(&self, keysym: Keysym) -> Result<(), String> {
+            let keysyms = vec![keysym; usize::from(self.keysyms_per_keycode)];
+            self.conn
+                .change_keyboard_mapping(1, self.keycode, self.keysyms_per_keycode, &keysyms)
+                .map_err(|err| err.to_string())?
+                .check()
+                .map_err(|err| err.to_string())
+        }
+    }
+
+    impl Drop for Typer {
+        fn drop(&mut self) {
+            if let Err(error) = self.map(NO_SYMBOL) {
+                eprintln!("Failed to release the keycode used for typing: {}", error);
+            }
+        }
+    }
+
+    /// Latin-1 characters have keysyms of the same value; everything else
+    /// uses the Unicode keysym range.
+    pub(super) fn keysym(ch: char) -> Keysym {
+        match ch as u32 {
+            code @ (0x20..=0x7e | 0xa0..=0xff) => code,
+            code => 0x0100_0000 | code,
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::KEY_EVENT_DELAY;
+    use core_graphics::{
+        event::{CGEvent, CGEventTapLocation},
+        event_source::{CGEventSource, CGEventSourceStateID},
+    };
+    use std::thread;
+
+    /// Posts key events whose text is the character. The keycode they carry
+    /// is ignored by apps that read the text, which is nearly all of them.
+    pub(super) struct Typer {
+        source: CGEventSource,
+    }
+
+    impl Typer {
+        pub(super) fn new() -> Result<Self, String> {
+            let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+                .map_err(|_| "Failed to create a keyboard event source".to_string())?;
+            Ok(Self { source })
+        }
+
+        pub(super) fn type_char(&mut self, ch: char) -> Result<(), String> {
+            let text = ch.to_string();
+            for key_down in [true, false] {
+                let event = CGEvent::new_keyboard_event(self.source.clone(), 0, key_down)
+                    .map_err(|_| "Failed to create a key event".to_string())?;
+                event.set_string(&text);
+                event.post(CGEventTapLocation::HID);
+                thread::sleep(KEY_EVENT_DELAY);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::KEY_EVENT_DELAY;
+    use std::{io, mem, thread};
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+        SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, KEYEVENTF_UNICODE,
+    };
+
+    /// Sends `KEYEVENTF_UNICODE` input, which reaches the app as the
+    /// character itself, one UTF-16 unit per event.
+    pub(super) struct Typer;
+
+    impl Typer {
+        pub(super) fn new() -> Result<Self, String> {
+            Ok(Self)
+        }
+
+        pub(super) fn type_char(&mut self, ch: char) -> Result<(), String> {
+            let mut units = [0u16; 2];
+            let units = ch.encode_utf16(&mut units);
+            for flags in [KEYEVENTF_UNICODE, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP] {
+                let inputs: Vec<INPUT> = units
+                    .iter()
+                    .map(|unit| INPUT {
+                        r#type: INPUT_KEYBOARD,
+                        Anonymous: INPUT_0 {
+                            ki: KEYBDINPUT {
+                                wVk: 0,
+                                wScan: *unit,
+                                dwFlags: flags,
+                                time: 0,
+                                dwExtraInfo: 0,
+                            },
+                        },
+                    })
+                    .collect();
+                let sent = unsafe {
+                    SendInput(
+                        inputs.len() as u32,
+                        inputs.as_ptr(),
+                        mem::size_of::<INPUT>() as i32,
+                    )
+                };
+                if sent as usize != inputs.len() {
+                    return Err(format!("Failed to type: {}", io::Error::last_os_error()));
+                }
+                thread::sleep(KEY_EVENT_DELAY);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plans_characters_and_newlines() {
+        assert_eq!(
+            plan_keystrokes("Hi!\r\nok\n"),
+            vec![
+                Keystroke::Char('H'),
+                Keystroke::Char('i'),
+                Keystroke::Char('!'),
+                Keystroke::Key(Key::Return),
+                Keystroke::Char('o'),
+                Keystroke::Char('k'),
+                Keystroke::Key(Key::Return),
+            ]
+        );
+    }
+
+    #[test]
+    fn characters_off_the_us_layout_are_characters_too() {
+        assert_eq!(
+            plan_keystrokes("é\t😀"),
+            vec![
+                Keystroke::Char('é'),
+                Keystroke::Key(Key::Tab),
+                Keystroke::Char('😀')
+            ]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn latin1_keeps_its_keysyms_and_the_rest_use_unicode_ones() {
+        assert_eq!(platform::keysym('a'), 0x61);
+        assert_eq!(platform::keysym('é'), 0xe9);
+        assert_eq!(platform::keysym('€'), 0x0100_20ac);
+        assert_eq!(platform::keysym('😀'), 0x0101_f600);
+    }
+}
@@ -1,10 +1,6 @@
 import { useEffect, useRef, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import { emit } from "@tauri-apps/api/event";
-import {
-  register as registerGlobalShortcut,
-  unregister as unregisterGlobalShortcut,
-} from "@tauri-apps/plugin-global-shortcut";
+import { listen } from "@tauri-apps/api/event";
 import "./App.css";
 
 const cameraIcon = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 24' fill='none' stroke='currentColor' stroke-width='2' stroke-linecap='round' stroke-linejoin='round'%3E%3Cpath d='M23 19a2 2 0 0 1-2 2H3a2 2 0 0 1-2-2V8a2 2 0 0 1 2-2h4l2-3h6l2 3h4a2 2 0 0 1 2 2z'/%3E%3Ccircle cx='12' cy='13' r='4'/%3E%3C/svg%3E";
@@ -13,7 +9,7 @@ const cursorIcon = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg'
 const plusIcon = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 24 24' fill='none' stroke='currentColor' stroke-width='2' stroke-linecap='round' stroke-linejoin='round'%3E%3Cline x1='12' y1='5' x2='12' y2='19'/%3E%3Cline x1='5' y1='12' x2='19' y2='12'/%3E%3C/svg%3E";
 
 const STORAGE_KEY = "gobuddy_presets_v1";
-const PRESETS_STATE_EVENT = "gobuddy://presets-state";
+const HOTKEY_DIAGNOSTICS_EVENT = "gobuddy://hotkey-diagnostics";
 
 type View = "screenshot" | "inputField" | "selection";
 const allViews: View[] = ["screenshot", "inputField", "selection"];
@@ -27,23 +23,138 @@ interface Preset {
   id: string;
   name: string;
   prompt: string;
+  model?: string;
+  temperature?: number;
+  maxTokens?: number;
+  outputMode?: OutputMode;
+  accelerator?: string;
+  chord?: string;
+}
+
+type ProviderKind = "openai" | "anthropic" | "ollama" | "openaiCompatible";
+
+interface ProviderConfig {
+  apiKey?: string;
+  baseUrl?: string;
+  model?: string;
+  models?: string[];
 }
 
 interface SettingsState {
   autoOpenOnStart: boolean;
   openaiApiKey: string;
+  openaiBaseUrl?: string;
+  provider?: ProviderKind;
+  providers?: Partial<Record<ProviderKind, ProviderConfig>>;
+  temperature?: number;
+  maxTokens?: number;
+  output?: OutputSettings;
+}
+
+type OutputMode = "paste" | "type";
+
+interface OutputSettings {
+  pasteDelayMs?: number;
+  restoreDelayMs?: number;
+  mode?: OutputMode;
+  appModes?: { app: string; mode: OutputMode }[];
+  typingRate?: number;
 }
 
 interface HotkeysState {
   screenshot: string;
+  inputField?: string;
+  selection?: string;
+  undo?: string;
 }
 
+// Unset fields fall back to the backend defaults.
+interface BehaviorSettings {
+  multiClickMs?: number;
+  dragThreshold?: number;
+  overlaySize?: number;
+  overlayOffsetRight?: number;
+  overlayOffsetLeft?: number;
+  overlayOffsetY?: number;
+  panelWidth?: number;
+  panelHeight?: number;
+}
+
+// Bounds mirror the ranges the backend validates on save.
+const behaviorFields: {
+  key: keyof BehaviorSettings;
+  label: string;
+  min: number;
+  max: number;
+  step: number;
+  placeholder: number;
+}[] = [
+  { key: "multiClickMs", label: "Double-Click Window (ms)", min: 100, max: 2000, step: 50, placeholder: 500 },
+  { key: "dragThreshold", label: "Drag Threshold (px)", min: 1, max: 50, step: 1, placeholder: 5 },
+  { key: "overlaySize", label: "Bubble Size (px)", min: 16, max: 128, step: 1, placeholder: 32 },
+  { key: "overlayOffsetRight", label: "Bubble Offset, Rightward Drag (px)", min: -300, max: 300, step: 1, placeholder: 10 },
+  { key: "overlayOffsetLeft", label: "Bubble Offset, Leftward Drag (px)", min: -300, max: 300, step: 1, placeholder: -50 },
+  { key: "overlayOffsetY", label: "Bubble Offset, Vertical (px)", min: -300, max: 300, step: 1, placeholder: 10 },
+  { key: "panelWidth", label: "Panel Width (px)", min: 80, max: 800, step: 10, placeholder: 120 },
+  { key: "panelHeight", label: "Panel Height (px)", min: 80, max: 1200, step: 10, placeholder: 180 },
+];
+
+type HotkeyTarget = "screenshot" | "inputField" | "selection" | "undo";
+
+// Hotkeys are recorded either for `HotkeysState` or for a single preset.
+type RecordingTarget = HotkeyTarget | `preset:${string}`;
+const PRESET_TARGET_PREFIX = "preset:";
+
+type HotkeyAction =
+  | { type: "view"; view: View }
+  | { type: "preset"; view: View; presetId: string; presetName: string }
+  | { type: "chord"; view: View; presetId: string; presetName: string }
+  | { type: "undo" };
+
+type HotkeyStatus = { action: HotkeyAction; accelerator: string } & (
+  | { state: "registered" | "paused" }
+  | { state: "failed"; message: string }
+  | { state: "invalid"; error: { kind: string; message: string } }
+);
+
+interface ElectronImportReport {
+  sources: string[];
+  imported: string[];
+  skipped: { item: string; reason: string }[];
+}
+
+// Key sequences are typed rather than recorded, so they have no target.
+const recordingTargetFor = (action: HotkeyAction): RecordingTarget | null => {
+  switch (action.type) {
+    case "view":
+      return action.view;
+    case "preset":
+      return `${PRESET_TARGET_PREFIX}${action.presetId}`;
+    case "chord":
+      return null;
+    case "undo":
+      return "undo";
+  }
+};
+
+const describeHotkeyFailure = (status: HotkeyStatus): string | null => {
+  switch (status.state) {
+    case "failed":
+      return `${status.accelerator} could not be registered; another app may be using it (${status.message}).`;
+    case "invalid":
+      return status.error.message;
+    default:
+      return null;
+  }
+};
+
 interface PersistedState {
   presets: Record<View, Preset[]>;
   nextPresetId: number;
   activePresetIds: Record<View, string | null>;
   settings?: SettingsState;
   hotkeys?: HotkeysState;
+  behavior?: BehaviorSettings;
 }
 
 const viewLabels: Record<View, string> = {
@@ -74,6 +185,7 @@ const createEmptyActivePresets = (): Record<View, string | null> => ({
 });
 
 const sanitizePreset = (preset: Partial<Preset> & { id: string }): Preset => ({
+  ...preset,
   id: preset.id,
   name: preset.name ?? "Untitled Preset",
   prompt: preset.prompt ?? "",
@@ -107,6 +219,7 @@ const deriveNextPresetId = (presets: Record<View, Preset[]>): number => {
 };
 
 const normalizeSettings = (settings: SettingsState | undefined): SettingsState => ({
+  ...settings,
   autoOpenOnStart:
     typeof settings?.autoOpenOnStart === "boolean"
       ? settings.autoOpenOnStart
@@ -118,6 +231,7 @@ const normalizeSettings = (settings: SettingsState | undefined): SettingsState =
 });
 
 const normalizeHotkeys = (hotkeys: HotkeysState | undefined): HotkeysState => ({
+  ...hotkeys,
   screenshot:
     typeof hotkeys?.screenshot === "string" ? hotkeys.screenshot : defaultHotkeys.screenshot,
 });
@@ -185,6 +299,49 @@ const toAccelerator = (display: string): string =>
     .filter((part) => part.length > 0)
     .join("+");
 
+// The backend only hands out API keys masked like this, and keeps the saved
+// key when a save sends the mask back unchanged.
+const SECRET_MASK = "••••••••";
+const SECRET_MASK_MIN_LENGTH = 12;
+
+const isMaskedSecret = (value: string | undefined): boolean =>
+  typeof value === "string" && value.startsWith(SECRET_MASK);
+
+const maskSecret = (value: string | undefined): string | undefined => {
+  if (!value || isMaskedSecret(value)) {
+    return value;
+  }
+  const characters = Array.from(value);
+  return characters.length < SECRET_MASK_MIN_LENGTH
+    ? SECRET_MASK
+    : `${SECRET_MASK}${characters.slice(-4).join("")}`;
+};
+
+// Anything running in the webview can read localStorage, so the copy kept
+// there never holds a usable key.
+const withMaskedSecrets = (state: PersistedState): PersistedState => {
+  if (!state.settings) {
+    return state;
+  }
+  const { openaiApiKey, providers } = state.settings;
+  const maskedProviders = providers
+    ? (Object.fromEntries(
+        Object.entries(providers).map(([kind, config]) => [
+          kind,
+          config && { ...config, apiKey: maskSecret(config.apiKey) },
+        ]),
+      ) as SettingsState["providers"])
+    : providers;
+  return {
+    ...state,
+    settings: {
+      ...state.settings,
+      openaiApiKey: maskSecret(openaiApiKey) ?? "",
+      providers: maskedProviders,
+    },
+  };
+};
+
 const isTauriEnvironment = (): boolean =>
   typeof window !== "undefined" &&
   Boolean((window as Window & { __TAURI_INTERNALS__?: unknown }).__TAURI_INTERNALS__);
@@ -198,6 +355,12 @@ const readPersistedState = async (): Promise<PersistedState | null> => {
     try {
       const state = (await invoke("load_presets_state")) as PersistedState | null;
       if (state) {
+        // Replaces a local copy that an older build left with plain-text keys.
+        try {
+          window.localStorage.setItem(STORAGE_KEY, JSON.stringify(withMaskedSecrets(state)));
+        } catch {
+          // Ignore local storage errors (e.g., quota)
+        }
         return state;
       }
     } catch (error) {
@@ -205,51 +368,59 @@ const readPersistedState = async (): Promise<PersistedState | null> => {
     }
   }
 
+  let stored: unknown;
   try {
     const raw = window.localStorage.getItem(STORAGE_KEY);
-    return raw ? (JSON.parse(raw) as PersistedState) : null;
+    stored = raw ? JSON.parse(raw) : null;
   } catch (error) {
     console.warn("Failed to parse local storage state", error);
     return null;
   }
-};
-
-const broadcastPresetState = async (state: PersistedState): Promise<void> => {
-  if (!isTauriEnvironment()) {
-    return;
+  if (!stored) {
+    return null;
   }
 
-  try {
-    await emit(PRESETS_STATE_EVENT, state);
-  } catch (error) {
-    console.warn("Failed to broadcast preset updates", error);
+  // The local copy may predate the backend's current schema; let it upgrade
+  // the copy the same way it upgrades its own file.
+  if (isTauriEnvironment()) {
+    try {
+      return (await invoke("migrate_presets_state", { state: stored })) as PersistedState;
+    } catch (error) {
+      console.warn("Failed to migrate local storage state", error);
+      return null;
+    }
   }
+  return stored as PersistedState;
 };
 
+// Rejects when the backend refused the save. The other windows only hear
+// about state the backend saved, which it broadcasts with the keys masked,
+// so there is nothing to tell them here.
 const writePersistedState = async (state: PersistedState): Promise<void> => {
   if (typeof window === "undefined") {
     return;
   }
 
-  let persistedViaTauri = false;
+  let saveError: unknown = null;
 
   if (isTauriEnvironment()) {
     try {
       await invoke("save_presets_state", { state });
-      persistedViaTauri = true;
     } catch (error) {
       console.warn("Failed to persist presets via Tauri command", error);
+      saveError = error;
     }
   }
 
   try {
-    window.localStorage.setItem(STORAGE_KEY, JSON.stringify(state));
+    const localCopy = isTauriEnvironment() ? withMaskedSecrets(state) : state;
+    window.localStorage.setItem(STORAGE_KEY, JSON.stringify(localCopy));
   } catch {
     // Ignore local storage errors (e.g., quota)
   }
 
-  if (!persistedViaTauri) {
-    void broadcastPresetState(state);
+  if (saveError !== null) {
+    throw saveError;
   }
 };
 
@@ -263,11 +434,50 @@ function App() {
   );
   const [settings, setSettings] = useState<SettingsState>(defaultSettings);
   const [hotkeys, setHotkeys] = useState<HotkeysState>(defaultHotkeys);
-  const [isRecordingHotkey, setIsRecordingHotkey] = useState(false);
+  const [behavior, setBehavior] = useState<BehaviorSettings>({});
+  const [recordingHotkey, setRecordingHotkey] = useState<RecordingTarget | null>(null);
   const [recordingDisplay, setRecordingDisplay] = useState("");
+  const [hotkeyStatuses, setHotkeyStatuses] = useState<HotkeyStatus[]>([]);
+
+  const [electronImport, setElectronImport] = useState<ElectronImportReport | null>(null);
+  const [electronImportError, setElectronImportError] = useState<string | null>(null);
+  const [saveError, setSaveError] = useState<string | null>(null);
 
   const hasHydratedRef = useRef(false);
-  const registeredScreenshotHotkeyRef = useRef<string | null>(null);
+
+  const applyStoredState = (stored: PersistedState) => {
+    const normalizedPresets = normalizePresets(stored.presets);
+    setPresets(normalizedPresets);
+
+    const storedNextPresetId = stored.nextPresetId;
+    if (typeof storedNextPresetId === "number" && storedNextPresetId > 0) {
+      setNextPresetId(storedNextPresetId);
+    } else {
+      setNextPresetId(deriveNextPresetId(normalizedPresets));
+    }
+
+    const normalizedActiveIds = normalizeActivePresetIds(stored.activePresetIds);
+    setActivePresetIds(normalizedActiveIds);
+
+    setSettings(normalizeSettings(stored.settings));
+    setHotkeys(normalizeHotkeys(stored.hotkeys));
+    setBehavior(stored.behavior ?? {});
+  };
+
+  const importElectronConfig = async () => {
+    setElectronImportError(null);
+    try {
+      const result = (await invoke("import_electron_config")) as {
+        report: ElectronImportReport;
+        state: PersistedState;
+      };
+      applyStoredState(result.state);
+      setElectronImport(result.report);
+    } catch (error) {
+      setElectronImport(null);
+      setElectronImportError(String(error));
+    }
+  };
 
   useEffect(() => {
     if (!isTauriEnvironment()) {
@@ -305,27 +515,14 @@ function App() {
         }
 
         if (stored) {
-          const normalizedPresets = normalizePresets(stored.presets);
-          setPresets(normalizedPresets);
-
-          const storedNextPresetId = stored.nextPresetId;
-          if (typeof storedNextPresetId === "number" && storedNextPresetId > 0) {
-            setNextPresetId(storedNextPresetId);
-          } else {
-            setNextPresetId(deriveNextPresetId(normalizedPresets));
-          }
-
-          const normalizedActiveIds = normalizeActivePresetIds(stored.activePresetIds);
-          setActivePresetIds(normalizedActiveIds);
-
-          setSettings(normalizeSettings(stored.settings));
-          setHotkeys(normalizeHotkeys(stored.hotkeys));
+          applyStoredState(stored);
         } else {
           setPresets(createEmptyPresets());
           setNextPresetId(1);
           setActivePresetIds(createEmptyActivePresets());
           setSettings(defaultSettings);
           setHotkeys(defaultHotkeys);
+          setBehavior({});
         }
       } catch (error) {
         if (!cancelled) {
@@ -335,6 +532,7 @@ function App() {
           setActivePresetIds(createEmptyActivePresets());
           setSettings(defaultSettings);
           setHotkeys(defaultHotkeys);
+          setBehavior({});
         }
       } finally {
         if (!cancelled) {
@@ -361,10 +559,13 @@ function App() {
       activePresetIds,
       settings,
       hotkeys,
+      behavior,
     };
 
-    void writePersistedState(payload);
-  }, [presets, nextPresetId, activePresetIds, settings, hotkeys]);
+    writePersistedState(payload)
+      .then(() => setSaveError(null))
+      .catch((error) => setSaveError(String(error)));
+  }, [presets, nextPresetId, activePresetIds, settings, hotkeys, behavior]);
 
   useEffect(() => {
     if (!hasHydratedRef.current) {
@@ -407,7 +608,7 @@ function App() {
   }, [presets]);
 
   useEffect(() => {
-    if (!isRecordingHotkey) {
+    if (!recordingHotkey) {
       return;
     }
 
@@ -421,7 +622,7 @@ function App() {
       event.stopPropagation();
 
       if (event.key === "Escape") {
-        setIsRecordingHotkey(false);
+        setRecordingHotkey(null);
         setRecordingDisplay("");
         return;
       }
@@ -430,11 +631,24 @@ function App() {
       const hasNonModifierKey = !isModifierKey(event.key);
 
       if (hasNonModifierKey) {
-        setHotkeys((prev) => ({
-          ...prev,
-          screenshot: hotkeyValue,
-        }));
-        setIsRecordingHotkey(false);
+        if (recordingHotkey.startsWith(PRESET_TARGET_PREFIX)) {
+          const presetId = recordingHotkey.slice(PRESET_TARGET_PREFIX.length);
+          setPresets((prev) => {
+            const next = { ...prev };
+            for (const view of allViews) {
+              next[view] = prev[view].map((preset) =>
+                preset.id === presetId ? { ...preset, accelerator: hotkeyValue } : preset,
+              );
+            }
+            return next;
+          });
+        } else {
+          setHotkeys((prev) => ({
+            ...prev,
+            [recordingHotkey as HotkeyTarget]: hotkeyValue,
+          }));
+        }
+        setRecordingHotkey(null);
         setRecordingDisplay("");
       } else {
         setRecordingDisplay(hotkeyValue);
@@ -442,7 +656,7 @@ function App() {
     };
 
     const handleWindowBlur = () => {
-      setIsRecordingHotkey(false);
+      setRecordingHotkey(null);
       setRecordingDisplay("");
     };
 
@@ -453,100 +667,49 @@ function App() {
       window.removeEventListener("keydown", handleKeyDown, true);
       window.removeEventListener("blur", handleWindowBlur);
     };
-  }, [isRecordingHotkey]);
+  }, [recordingHotkey]);
 
   useEffect(() => {
-    if (!isRecordingHotkey) {
+    if (!recordingHotkey) {
       setRecordingDisplay("");
     }
-  }, [isRecordingHotkey]);
+  }, [recordingHotkey]);
 
   useEffect(() => {
-    if (!hasHydratedRef.current || !isTauriEnvironment()) {
-      return;
-    }
-
-    const nextDisplay = hotkeys.screenshot.trim();
-    const currentDisplay = registeredScreenshotHotkeyRef.current;
-    const nextAccelerator = nextDisplay ? toAccelerator(nextDisplay) : null;
-    const currentAccelerator = currentDisplay ? toAccelerator(currentDisplay) : null;
-
-    if (currentAccelerator === nextAccelerator) {
+    if (!isTauriEnvironment()) {
       return;
     }
 
     let isDisposed = false;
-
-    const updateShortcut = async () => {
-      if (currentAccelerator) {
-        try {
-          await unregisterGlobalShortcut(currentAccelerator);
-        } catch (error) {
-          console.warn(
-            `Failed to unregister previous screenshot shortcut "${currentAccelerator}"`,
-            error,
-          );
-        } finally {
-          if (!isDisposed) {
-            registeredScreenshotHotkeyRef.current = null;
-          }
-        }
-      }
-
-      if (!nextAccelerator) {
-        return;
-      }
-
-      try {
-        await registerGlobalShortcut(nextAccelerator, (event) => {
-          if (event.state !== "Pressed") {
-            return;
-          }
-
-          window.dispatchEvent(
-            new CustomEvent("gobuddy:screenshot-triggered", {
-              detail: event,
-            }),
-          );
-        });
-
+    const unlistenPromise = listen<{ failures: HotkeyStatus[] }>(
+      HOTKEY_DIAGNOSTICS_EVENT,
+      (event) => setHotkeyStatuses(event.payload.failures),
+    );
+    // Startup registration happens before this window listens, so ask once.
+    void invoke<HotkeyStatus[]>("get_hotkey_status")
+      .then((statuses) => {
         if (!isDisposed) {
-          registeredScreenshotHotkeyRef.current = nextDisplay;
-        } else {
-          await unregisterGlobalShortcut(nextAccelerator);
+          setHotkeyStatuses(statuses);
         }
-      } catch (error) {
-        console.warn(`Failed to register screenshot shortcut "${nextAccelerator}"`, error);
-      }
-    };
-
-    void updateShortcut();
+      })
+      .catch((error) => console.warn("Failed to load hotkey status", error));
 
     return () => {
       isDisposed = true;
+      void unlistenPromise.then((unlisten) => unlisten());
     };
-  }, [hotkeys.screenshot]);
+  }, []);
 
   useEffect(() => {
-    return () => {
-      if (!isTauriEnvironment()) {
-        return;
-      }
-
-      const currentDisplay = registeredScreenshotHotkeyRef.current;
-      if (!currentDisplay) {
-        return;
-      }
+    if (!isTauriEnvironment()) {
+      return;
+    }
 
-      registeredScreenshotHotkeyRef.current = null;
-      void unregisterGlobalShortcut(toAccelerator(currentDisplay)).catch((error) => {
-        console.warn(
-          `Failed to clean up screenshot shortcut "${toAccelerator(currentDisplay)}"`,
-          error,
-        );
-      });
-    };
-  }, []);
+    // Release the global hotkeys while recording so their keys reach this window.
+    void invoke("set_hotkeys_paused", { paused: recordingHotkey !== null }).catch((error) => {
+      console.warn("Failed to pause global hotkeys", error);
+    });
+  }, [recordingHotkey]);
 
   const handleSectionNavClick = (view: View) => {
     setActiveView(view);
@@ -642,55 +805,135 @@ function App() {
       ? presets[activePanel.view].find((preset) => preset.id === activePanel.presetId) ?? null
       : null;
 
-  const renderScreenshotConfig = () => (
-    <div className="section-config">
-      <h1>{viewLabels.screenshot} Configuration</h1>
-      <p className="section-description">
-        Set up how screenshots behave when triggered from the shortcut.
-      </p>
-
+  const renderHotkeyCard = (
+    target: RecordingTarget,
+    label: string,
+    description: string,
+    value: string | undefined,
+    fallback = "No hotkey recorded yet",
+    onClear?: () => void,
+  ) => {
+    const isRecording = recordingHotkey === target;
+    const failure = hotkeyStatuses
+      .filter((status) => recordingTargetFor(status.action) === target)
+      .map(describeHotkeyFailure)
+      .find((message) => message !== null);
+    return (
       <div className="hotkey-card">
         <div className="hotkey-text">
-          <span className="hotkey-label">Capture Hotkey</span>
-          <p className="hotkey-description">
-            Choose the key combination that captures a screenshot. Press escape to cancel recording.
-          </p>
+          <span className="hotkey-label">{label}</span>
+          <p className="hotkey-description">{description}</p>
           <div className="hotkey-display">
-            {isRecordingHotkey
-              ? recordingDisplay || "Press keys..."
-              : hotkeys.screenshot || "No hotkey recorded yet"}
+            {isRecording ? recordingDisplay || "Press keys..." : value || fallback}
           </div>
+          {failure && !isRecording ? <p className="hotkey-warning">{failure}</p> : null}
         </div>
         <button
           type="button"
-          className={`record-hotkey-button ${isRecordingHotkey ? "recording" : ""}`}
+          className={`record-hotkey-button ${isRecording ? "recording" : ""}`}
           onClick={() => {
-            if (isRecordingHotkey) {
-              setIsRecordingHotkey(false);
-              setRecordingDisplay("");
-            } else {
-              setRecordingDisplay("");
-              setIsRecordingHotkey(true);
-            }
+            setRecordingDisplay("");
+            setRecordingHotkey(isRecording ? null : target);
           }}
         >
-          {isRecordingHotkey ? "Recording..." : "Record Hotkey"}
+          {isRecording ? "Recording..." : "Record Hotkey"}
         </button>
+        {onClear && value && !isRecording ? (
+          <button type="button" className="record-hotkey-button" onClick={onClear}>
+            Clear
+          </button>
+        ) : null}
       </div>
+    );
+  };
+
+  const renderScreenshotConfig = () => (
+    <div className="section-config">
+      <h1>{viewLabels.screenshot} Configuration</h1>
+      <p className="section-description">
+        Set up how screenshots behave when triggered from the shortcut.
+      </p>
+
+      {renderHotkeyCard(
+        "screenshot",
+        "Capture Hotkey",
+        "Choose the key combination that captures a screenshot. Press escape to cancel recording.",
+        hotkeys.screenshot,
+      )}
     </div>
   );
 
   const renderInputFieldConfig = () => (
     <div className="section-config">
       <h1>{viewLabels.inputField} Configuration</h1>
-      <p className="section-description">No additional configuration available yet.</p>
+      <p className="section-description">
+        Rewrite the selected text in place with the active preset.
+      </p>
+
+      {renderHotkeyCard(
+        "inputField",
+        "Rewrite Hotkey",
+        "Choose the key combination that runs the active preset on the selected text. Press escape to cancel recording.",
+        hotkeys.inputField,
+      )}
     </div>
   );
 
   const renderSelectionConfig = () => (
     <div className="section-config">
       <h1>{viewLabels.selection} Configuration</h1>
-      <p className="section-description">No additional configuration available yet.</p>
+      <p className="section-description">
+        Show the active preset's answer for the selected text in a result window.
+      </p>
+
+      {renderHotkeyCard(
+        "selection",
+        "Ask Hotkey",
+        "Choose the key combination that runs the active preset on the selected text. Press escape to cancel recording.",
+        hotkeys.selection,
+      )}
+
+      <p className="hotkey-description">
+        Drags longer than the threshold, and double- or triple-click selections, show the bubble.
+        Clicks closer together than the double-click window count as one double or triple click.
+        Leave a field empty to use its default.
+      </p>
+
+      {behaviorFields.map((field) => {
+        const value = behavior[field.key];
+        const outOfRange = value !== undefined && (value < field.min || value > field.max);
+        return (
+          <div className="field-group" key={field.key}>
+            <label className="field-label" htmlFor={`behavior-${field.key}`}>
+              {field.label}
+            </label>
+            <input
+              id={`behavior-${field.key}`}
+              className="text-input"
+              type="number"
+              min={field.min}
+              max={field.max}
+              step={field.step}
+              value={value ?? ""}
+              onChange={(event) => {
+                const parsed = Number.parseFloat(event.target.value);
+                // The backend stores the click window in whole milliseconds.
+                const rounded = field.key === "multiClickMs" ? Math.round(parsed) : parsed;
+                setBehavior((prev) => ({
+                  ...prev,
+                  [field.key]: Number.isNaN(rounded) ? undefined : rounded,
+                }));
+              }}
+              placeholder={String(field.placeholder)}
+            />
+            {outOfRange ? (
+              <p className="hotkey-warning">
+                Must be between {field.min} and {field.max}; changes aren't saved until it is.
+              </p>
+            ) : null}
+          </div>
+        );
+      })}
     </div>
   );
 
@@ -734,15 +977,69 @@ function App() {
             id="openai-api-key"
             className="text-input"
             type="password"
-            value={settings.openaiApiKey}
+            value={isMaskedSecret(settings.openaiApiKey) ? "" : settings.openaiApiKey}
             onChange={(event) =>
               setSettings((prev) => ({
                 ...prev,
                 openaiApiKey: event.target.value,
               }))
             }
-            placeholder="Enter your OpenAI API key"
+            placeholder={
+              isMaskedSecret(settings.openaiApiKey)
+                ? `Saved key ${settings.openaiApiKey}. Type to replace it.`
+                : "Enter your OpenAI API key"
+            }
           />
+          <p className="hotkey-description">
+            Stored encrypted on this device, separately from your presets.
+          </p>
+        </div>
+      </div>
+
+      <div className="settings-group">
+        {renderHotkeyCard(
+          "undo",
+          "Undo Hotkey",
+          "Puts back the text GoBuddy last replaced in place. Press escape to cancel recording.",
+          hotkeys.undo,
+          "CommandOrControl + Alt + Z",
+        )}
+      </div>
+
+      <div className="settings-group">
+        <div className="field-group">
+          <span className="field-label">Import from the Electron App</span>
+          <p className="hotkey-description">
+            Adds the presets, hotkeys and API key of the previous GoBuddy app. Anything already
+            set up here is kept.
+          </p>
+          <button
+            type="button"
+            className="record-hotkey-button"
+            onClick={() => void importElectronConfig()}
+          >
+            Import
+          </button>
+          {electronImportError ? <p className="hotkey-warning">{electronImportError}</p> : null}
+          {electronImport ? (
+            <div className="import-report">
+              <p className="hotkey-description">
+                {electronImport.imported.length === 0
+                  ? "Nothing new to import."
+                  : `Imported ${electronImport.imported.length} item(s) from ${electronImport.sources.length} file(s).`}
+              </p>
+              <ul>
+                {electronImport.imported.map((item) => (
+                  <li key={`imported-${item}`}>{item}</li>
+                ))}
+                {electronImport.skipped.map((skipped) => (
+                  <li key={`skipped-${skipped.item}`} className="import-skipped">
+                    Skipped {skipped.item}: {skipped.reason}
+                  </li>
+                ))}
+              </ul>
+            </div>
+          ) : null}
         </div>
       </div>
     </div>
@@ -760,6 +1057,13 @@ function App() {
 
     const nameInputId = `preset-name-${activePreset.id}`;
     const promptInputId = `preset-prompt-${activePreset.id}`;
+    const chordInputId = `preset-chord-${activePreset.id}`;
+    const chordFailure = hotkeyStatuses
+      .filter(
+        (status) => status.action.type === "chord" && status.action.presetId === activePreset.id,
+      )
+      .map(describeHotkeyFailure)
+      .find((message) => message !== null);
 
     return (
       <div className="preset-editor">
@@ -811,6 +1115,37 @@ function App() {
             rows={8}
           />
         </div>
+
+        {renderHotkeyCard(
+          `${PRESET_TARGET_PREFIX}${activePreset.id}`,
+          "Preset Hotkey",
+          "Runs this preset directly on the selected text, without opening the panel. Press escape to cancel recording.",
+          activePreset.accelerator,
+          "No hotkey recorded yet",
+          () => updatePreset(activePanel.view, activePreset.id, { accelerator: undefined }),
+        )}
+
+        <div className="field-group">
+          <label className="field-label" htmlFor={chordInputId}>
+            Key Sequence
+          </label>
+          <input
+            id={chordInputId}
+            className="text-input"
+            value={activePreset.chord ?? ""}
+            onChange={(event) =>
+              updatePreset(activePanel.view, activePreset.id, {
+                chord: event.target.value === "" ? undefined : event.target.value,
+              })
+            }
+            placeholder="Ctrl + Space, T"
+          />
+          <p className="hotkey-description">
+            Press the steps one after another, each within a second. The keys still reach the
+            focused app, so prefer steps it ignores.
+          </p>
+          {chordFailure ? <p className="hotkey-warning">{chordFailure}</p> : null}
+        </div>
       </div>
     );
   };
@@ -924,7 +1259,12 @@ function App() {
           </button>
         </div>
       </aside>
-      <main className="right-page">{renderContent()}</main>
+      <main className="right-page">
+        {saveError ? (
+          <p className="save-warning">Your changes couldn't be saved: {saveError}</p>
+        ) : null}
+        {renderContent()}
+      </main>
     </div>
   );
 }
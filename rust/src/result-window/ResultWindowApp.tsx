@@ -0,0 +1,127 @@
+import { useCallback, useEffect, useRef, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import "./result-window.css";
+
+const COMPLETION_STREAM_EVENT = "gobuddy://completion-stream";
+
+type StreamFrame =
+  | { kind: "start"; requestId: number; presetId: string }
+  | { kind: "delta"; requestId: number; text: string }
+  | { kind: "done"; requestId: number; output: string }
+  | { kind: "error"; requestId: number; message: string }
+  | { kind: "cancelled"; requestId: number };
+
+type Status = "idle" | "streaming" | "done" | "error" | "cancelled";
+
+export function ResultWindowApp() {
+  const [output, setOutput] = useState("");
+  const [status, setStatus] = useState<Status>("idle");
+  const [errorMessage, setErrorMessage] = useState<string | null>(null);
+  const requestIdRef = useRef<number | null>(null);
+  const unlistenRef = useRef<UnlistenFn | null>(null);
+
+  const applyFrame = useCallback((frame: StreamFrame) => {
+    if (frame.kind === "start") {
+      requestIdRef.current = frame.requestId;
+      setOutput("");
+      setErrorMessage(null);
+      setStatus("streaming");
+      return;
+    }
+    if (frame.requestId !== requestIdRef.current) {
+      return;
+    }
+    switch (frame.kind) {
+      case "delta":
+        setOutput((previous) => previous + frame.text);
+        break;
+      case "done":
+        setOutput(frame.output);
+        setStatus("done");
+        break;
+      case "error":
+        setErrorMessage(frame.message);
+        setStatus("error");
+        break;
+      case "cancelled":
+        setStatus("cancelled");
+        break;
+    }
+  }, []);
+
+  const closeWindow = useCallback(async () => {
+    try {
+      await invoke("hide_result_window");
+    } catch (error) {
+      console.warn("Failed to close result window", error);
+    }
+  }, []);
+
+  const copyOutput = useCallback(async () => {
+    try {
+      await navigator.clipboard.writeText(output);
+    } catch (error) {
+      console.warn("Failed to copy result", error);
+    }
+  }, [output]);
+
+  useEffect(() => {
+    let disposed = false;
+    listen<StreamFrame>(COMPLETION_STREAM_EVENT, (event) => {
+      applyFrame(event.payload);
+    })
+      .then((unlisten) => {
+        if (disposed) {
+          unlisten();
+        } else {
+          unlistenRef.current = unlisten;
+        }
+      })
+      .catch((error) => {
+        console.warn("Failed to listen for completion stream", error);
+      });
+
+    return () => {
+      disposed = true;
+      if (unlistenRef.current) {
+        unlistenRef.current();
+        unlistenRef.current = null;
+      }
+    };
+  }, [applyFrame]);
+
+  useEffect(() => {
+    const handleKeyDown = (event: KeyboardEvent) => {
+      if (event.key === "Escape") {
+        event.preventDefault();
+        void closeWindow();
+      }
+    };
+    window.addEventListener("keydown", handleKeyDown);
+    return () => window.removeEventListener("keydown", handleKeyDown);
+  }, [closeWindow]);
+
+  return (
+    <div className="result-panel">
+      <div className="result-header">
+        <span className={`result-status ${status}`}>
+          {status === "streaming" ? "Thinking…" : status === "cancelled" ? "Cancelled" : "GoBuddy"}
+        </span>
+        <div className="result-actions">
+          <button type="button" onClick={() => void copyOutput()} disabled={!output}>
+            Copy
+          </button>
+          <button type="button" onClick={() => void closeWindow()}>
+            Close
+          </button>
+        </div>
+      </div>
+      <div className="result-body">
+        {errorMessage ? <p className="result-error">{errorMessage}</p> : <p>{output}</p>}
+      </div>
+    </div>
+  );
+}
+
+export default ResultWindowApp;
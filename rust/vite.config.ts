@@ -16,6 +16,7 @@ export default defineConfig(async () => ({
         main: resolve(rootDir, "index.html"),
         overlay: resolve(rootDir, "overlay.html"),
         floating: resolve(rootDir, "floating-window.html"),
+        result: resolve(rootDir, "result-window.html"),
       },
     },
   },